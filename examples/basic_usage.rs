@@ -1,21 +1,36 @@
+use grok_client::filters;
 use grok_client::prelude::*;
+use std::time::Duration;
 
 #[tokio::main]
 async fn main() -> Result<(), GrokError> {
     env_logger::init();
 
-    let config = GrokConfig::new(
-        ************,          // Ваш API_ID
-        "*********************", // Ваш API_HASH
-        "GrokAI",      // Имя бота без @
-        "session.session"
-    );
+    // API_ID, API_HASH, BOT_USERNAME и т.д. берутся из .env
+    let config = GrokConfig::from_dotenv(".env")?;
 
     let client = GrokClient::new(config).await?;
-    client.start();
+    let print_bot = handler_fn(|ctx: MessageContext| async move {
+        if !ctx.is_edit() {
+            println!("\n[Bot]: {}", ctx.text());
+        }
+        Propagation::Continue
+    });
+    client.add_custom_handler(print_bot.with_filter(filters::from_bot()));
+    let handle = client.start();
 
     client.send("Hello!", RequestPriority::High).await?; // "Hello!" зпменить на текстовый вход
 
+    // Ожидание ответа бота на конкретный запрос
+    let reply = client.ask("What can you do?", RequestPriority::Normal).await?;
+    log::info!("Reply to our question: {}", reply.text);
+
     tokio::signal::ctrl_c().await?;
+
+    // Даём очереди до 10 секунд на отправку оставшихся сообщений
+    let report = handle.shutdown(Duration::from_secs(10)).await;
+    for (message, priority) in &report.unsent {
+        log::warn!("Not sent ({:?}): {}", priority, message.text);
+    }
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,74 @@
+//! The logged-in account and its sessions on other devices.
+
+use std::time::SystemTime;
+
+use crate::{
+    client::GrokClient,
+    connection::ConnectionState,
+    error::GrokError,
+    events::ClientEvent,
+};
+
+/// The account the client is logged in as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Account {
+    pub id: i64,
+    pub first_name: String,
+    pub last_name: Option<String>,
+    pub username: Option<String>,
+    pub phone: Option<String>,
+    pub bot: bool,
+}
+
+/// A logged-in session of the account, as listed under Settings → Devices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Authorization {
+    /// Identifies the session to [`GrokClient::terminate_authorization`].
+    pub hash: i64,
+    /// Whether this is the client's own session.
+    pub current: bool,
+    pub device_model: String,
+    pub platform: String,
+    pub system_version: String,
+    pub app_name: String,
+    pub app_version: String,
+    pub ip: String,
+    pub country: String,
+    pub created: SystemTime,
+    pub last_active: SystemTime,
+}
+
+impl GrokClient {
+    pub async fn get_me(&self) -> Result<Account, GrokError> {
+        self.transport.get_me().await
+    }
+
+    /// Every session the account is logged in with, this one included.
+    pub async fn authorizations(&self) -> Result<Vec<Authorization>, GrokError> {
+        self.transport.authorizations().await
+    }
+
+    /// Logs out the session with `hash`. Use [`GrokClient::log_out`] for
+    /// the client's own session.
+    pub async fn terminate_authorization(&self, hash: i64) -> Result<(), GrokError> {
+        self.transport.terminate_authorization(hash).await
+    }
+
+    /// Logs out every session except the client's own.
+    pub async fn terminate_other_authorizations(&self) -> Result<(), GrokError> {
+        self.transport.terminate_other_authorizations().await
+    }
+
+    /// Revokes the session on Telegram's side and wipes the saved copy.
+    ///
+    /// The client cannot send anything afterwards; its connection state
+    /// becomes [`ConnectionState::AuthRequired`].
+    pub async fn log_out(&self) -> Result<(), GrokError> {
+        self.transport.log_out().await?;
+        self.state.send_replace(ConnectionState::AuthRequired);
+        self.events
+            .emit(ClientEvent::ConnectionStateChanged(ConnectionState::AuthRequired));
+        log::info!("Logged out");
+        Ok(())
+    }
+}
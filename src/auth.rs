@@ -0,0 +1,295 @@
+//! Asking a person for the phone number, login code and 2FA password.
+
+use async_trait::async_trait;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::error::GrokError;
+
+mod qr;
+
+pub use qr::QrLogin;
+
+/// How to sign in when the session is not authorized.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoginMethod {
+    /// Ask for the phone number and the code Telegram sends to it.
+    #[default]
+    Code,
+    /// Show a QR code to scan with a logged-in Telegram app, so nobody has
+    /// to type a code in time.
+    Qr,
+}
+
+/// Where Telegram sent the login code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeDelivery {
+    /// A message in another logged-in Telegram app.
+    App,
+    Sms,
+    Call,
+    Email,
+    /// The transport cannot tell.
+    Unknown,
+}
+
+/// Supplies what signing in needs. Used when the session is not authorized.
+#[async_trait]
+pub trait AuthPrompter: Send + Sync {
+    /// The account's phone number in international format, e.g. `+1234567890`.
+    async fn phone(&self) -> Result<String, GrokError>;
+
+    async fn code(&self, delivery: CodeDelivery) -> Result<String, GrokError>;
+
+    /// The 2FA password. `hint` is the hint set with the password, if any.
+    async fn password(&self, hint: Option<&str>) -> Result<String, GrokError>;
+
+    /// Shows a QR code for [`LoginMethod::Qr`]. Called again with a new code
+    /// each time the previous one expires. Fails by default, so QR login
+    /// needs a prompter that overrides it.
+    async fn show_qr(&self, _qr: &QrLogin) -> Result<(), GrokError> {
+        Err(GrokError::Auth(
+            "QR login needs a prompter that can show QR codes".into(),
+        ))
+    }
+}
+
+/// Prompts on stdout and reads answers from stdin.
+#[derive(Debug, Default)]
+pub struct TerminalPrompter;
+
+impl TerminalPrompter {
+    async fn ask(prompt: String) -> Result<String, GrokError> {
+        tokio::task::spawn_blocking(move || {
+            println!("{}", prompt);
+            io::stdout().flush()?;
+            let mut input = String::new();
+            io::stdin().lock().read_line(&mut input)?;
+            Ok(input.trim().to_string())
+        })
+        .await
+        .map_err(|e| GrokError::Auth(e.to_string()))?
+    }
+}
+
+#[async_trait]
+impl AuthPrompter for TerminalPrompter {
+    async fn phone(&self) -> Result<String, GrokError> {
+        Self::ask("Enter phone number (e.g. +1234567890):".into()).await
+    }
+
+    async fn code(&self, delivery: CodeDelivery) -> Result<String, GrokError> {
+        let prompt = match delivery {
+            CodeDelivery::App => "Enter the code sent to your Telegram app:",
+            CodeDelivery::Sms => "Enter the code sent by SMS:",
+            CodeDelivery::Call => "Enter the code you were called with:",
+            CodeDelivery::Email => "Enter the code sent to your email:",
+            CodeDelivery::Unknown => "Enter Telegram code:",
+        };
+        Self::ask(prompt.into()).await
+    }
+
+    async fn password(&self, hint: Option<&str>) -> Result<String, GrokError> {
+        match hint {
+            Some(hint) => Self::ask(format!("Enter 2FA password (hint: {}):", hint)).await,
+            None => Self::ask("Enter 2FA password:".into()).await,
+        }
+    }
+
+    async fn show_qr(&self, qr: &QrLogin) -> Result<(), GrokError> {
+        println!("Scan this QR code in Telegram under Settings > Devices > Link Desktop Device:");
+        println!("{}", qr.to_terminal());
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Source {
+    Value(String),
+    Var(String),
+    /// Polled until it exists and is not empty, since the code is only
+    /// known after it has been sent.
+    File(PathBuf),
+}
+
+/// Reads answers from environment variables or files, for services.
+///
+/// By default the phone number, code and password come from `GROK_PHONE`,
+/// `GROK_LOGIN_CODE` and `GROK_PASSWORD`.
+#[derive(Debug, Clone)]
+pub struct EnvPrompter {
+    phone: Source,
+    code: Source,
+    password: Source,
+    file_timeout: Duration,
+}
+
+impl Default for EnvPrompter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EnvPrompter {
+    pub fn new() -> Self {
+        Self {
+            phone: Source::Var("GROK_PHONE".into()),
+            code: Source::Var("GROK_LOGIN_CODE".into()),
+            password: Source::Var("GROK_PASSWORD".into()),
+            file_timeout: Duration::from_secs(300),
+        }
+    }
+
+    pub fn phone(mut self, phone: impl Into<String>) -> Self {
+        self.phone = Source::Value(phone.into());
+        self
+    }
+
+    /// Waits for the code to be written to `path`.
+    pub fn code_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.code = Source::File(path.into());
+        self
+    }
+
+    pub fn password_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.password = Source::File(path.into());
+        self
+    }
+
+    /// How long to wait for a file to appear. Five minutes by default.
+    pub fn file_timeout(mut self, timeout: Duration) -> Self {
+        self.file_timeout = timeout;
+        self
+    }
+
+    async fn read(&self, source: &Source) -> Result<String, GrokError> {
+        match source {
+            Source::Value(value) => Ok(value.clone()),
+            Source::Var(name) => std::env::var(name)
+                .map(|value| value.trim().to_string())
+                .map_err(|_| GrokError::Auth(format!("{} is not set", name))),
+            Source::File(path) => {
+                let deadline = tokio::time::Instant::now() + self.file_timeout;
+                loop {
+                    if let Ok(text) = tokio::fs::read_to_string(path).await {
+                        if !text.trim().is_empty() {
+                            return Ok(text.trim().to_string());
+                        }
+                    }
+                    if tokio::time::Instant::now() >= deadline {
+                        return Err(GrokError::Auth(format!(
+                            "Nothing was written to {} within {:?}",
+                            path.display(),
+                            self.file_timeout
+                        )));
+                    }
+                    tokio::time::sleep(Duration::from_millis(500)).await;
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AuthPrompter for EnvPrompter {
+    async fn phone(&self) -> Result<String, GrokError> {
+        self.read(&self.phone).await
+    }
+
+    async fn code(&self, _delivery: CodeDelivery) -> Result<String, GrokError> {
+        self.read(&self.code).await
+    }
+
+    async fn password(&self, _hint: Option<&str>) -> Result<String, GrokError> {
+        self.read(&self.password).await
+    }
+}
+
+/// What a [`ChannelPrompter`] is asking for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthQuestion {
+    Phone,
+    Code(CodeDelivery),
+    Password { hint: Option<String> },
+    /// A QR code to show. Needs no answer.
+    Qr(QrLogin),
+}
+
+/// A question from a [`ChannelPrompter`], waiting for an answer.
+#[derive(Debug)]
+pub struct AuthRequest {
+    pub question: AuthQuestion,
+    answer: oneshot::Sender<String>,
+}
+
+impl AuthRequest {
+    pub fn answer(self, value: impl Into<String>) {
+        let _ = self.answer.send(value.into());
+    }
+}
+
+/// Forwards each question over a channel, so a web UI or a test can answer.
+///
+/// Dropping an [`AuthRequest`] without answering fails sign-in.
+pub struct ChannelPrompter {
+    questions: mpsc::UnboundedSender<AuthRequest>,
+}
+
+impl ChannelPrompter {
+    pub fn new() -> (Self, AuthRequests) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Self { questions: tx }, AuthRequests(rx))
+    }
+
+    async fn ask(&self, question: AuthQuestion) -> Result<String, GrokError> {
+        let (tx, rx) = oneshot::channel();
+        let request = AuthRequest {
+            question,
+            answer: tx,
+        };
+        self.questions
+            .send(request)
+            .map_err(|_| GrokError::Auth("Nobody is answering sign-in questions".into()))?;
+        rx.await
+            .map_err(|_| GrokError::Auth("Sign-in question was not answered".into()))
+    }
+
+    fn tell(&self, question: AuthQuestion) -> Result<(), GrokError> {
+        let (answer, _) = oneshot::channel();
+        self.questions
+            .send(AuthRequest { question, answer })
+            .map_err(|_| GrokError::Auth("Nobody is answering sign-in questions".into()))
+    }
+}
+
+#[async_trait]
+impl AuthPrompter for ChannelPrompter {
+    async fn phone(&self) -> Result<String, GrokError> {
+        self.ask(AuthQuestion::Phone).await
+    }
+
+    async fn code(&self, delivery: CodeDelivery) -> Result<String, GrokError> {
+        self.ask(AuthQuestion::Code(delivery)).await
+    }
+
+    async fn password(&self, hint: Option<&str>) -> Result<String, GrokError> {
+        let hint = hint.map(str::to_string);
+        self.ask(AuthQuestion::Password { hint }).await
+    }
+
+    async fn show_qr(&self, qr: &QrLogin) -> Result<(), GrokError> {
+        self.tell(AuthQuestion::Qr(qr.clone()))
+    }
+}
+
+/// The receiving end of a [`ChannelPrompter`].
+pub struct AuthRequests(mpsc::UnboundedReceiver<AuthRequest>);
+
+impl AuthRequests {
+    /// The next question, or `None` once the prompter is dropped.
+    pub async fn next(&mut self) -> Option<AuthRequest> {
+        self.0.recv().await
+    }
+}
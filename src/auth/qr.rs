@@ -0,0 +1,80 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use qrcode::render::unicode::Dense1x2;
+use qrcode::{Color, QrCode};
+use std::time::{Duration, SystemTime};
+
+/// A login token to show as a QR code. Scanning it with a logged-in
+/// Telegram app, under Settings → Devices, signs this client in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QrLogin {
+    token: Vec<u8>,
+    expires: SystemTime,
+}
+
+impl QrLogin {
+    pub fn new(token: Vec<u8>, expires: SystemTime) -> Self {
+        Self { token, expires }
+    }
+
+    /// The `tg://login` link the QR code encodes.
+    pub fn url(&self) -> String {
+        format!("tg://login?token={}", URL_SAFE_NO_PAD.encode(&self.token))
+    }
+
+    /// When the token stops working; a new one is shown after that.
+    pub fn expires(&self) -> SystemTime {
+        self.expires
+    }
+
+    pub(crate) fn expires_in(&self) -> Duration {
+        self.expires
+            .duration_since(SystemTime::now())
+            .unwrap_or_default()
+    }
+
+    /// The QR code drawn with block characters, for a dark terminal.
+    pub fn to_terminal(&self) -> String {
+        // Blocks are drawn in the foreground colour, which is light on a dark
+        // terminal, so they stand for the light modules
+        self.code()
+            .render::<Dense1x2>()
+            .dark_color(Dense1x2::Light)
+            .light_color(Dense1x2::Dark)
+            .build()
+    }
+
+    /// The QR code as a greyscale PNG image.
+    pub fn to_png(&self) -> Vec<u8> {
+        const SCALE: usize = 8;
+        const BORDER: usize = 4;
+
+        let code = self.code();
+        let width = code.width();
+        let size = (width + 2 * BORDER) * SCALE;
+        let mut pixels = vec![u8::MAX; size * size];
+        for (i, color) in code.to_colors().into_iter().enumerate() {
+            if color == Color::Dark {
+                let (x, y) = ((i % width + BORDER) * SCALE, (i / width + BORDER) * SCALE);
+                for row in y..y + SCALE {
+                    pixels[row * size + x..row * size + x + SCALE].fill(0);
+                }
+            }
+        }
+
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, size as u32, size as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        // Writing a correctly sized image to memory cannot fail
+        let mut writer = encoder.write_header().expect("PNG header");
+        writer.write_image_data(&pixels).expect("PNG data");
+        writer.finish().expect("PNG end");
+        png
+    }
+
+    fn code(&self) -> QrCode {
+        // A login link is far below the capacity of a QR code
+        QrCode::new(self.url()).expect("login link fits in a QR code")
+    }
+}
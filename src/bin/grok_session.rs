@@ -0,0 +1,177 @@
+//! Manages encrypted session files.
+//!
+//! ```text
+//! grok-session keygen <keyfile>
+//! grok-session encrypt <session> [--out <path>] [--keyfile <path>]
+//! ```
+//!
+//! Without `--keyfile` the passphrase is read from `GROK_SESSION_PASSPHRASE`,
+//! or asked for twice on the terminal without echoing it.
+
+use grok_client::session::{encrypt_session_file, SessionKey, SessionLock};
+use grok_client::GrokError;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage:
+  grok-session keygen <keyfile>
+  grok-session encrypt <session> [--out <path>] [--keyfile <path>]";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Error::Usage(message)) => {
+            eprintln!("{}\n{}", message, USAGE);
+            ExitCode::from(2)
+        }
+        Err(Error::Grok(e)) => {
+            eprintln!("grok-session: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+enum Error {
+    Usage(String),
+    Grok(GrokError),
+}
+
+impl From<GrokError> for Error {
+    fn from(e: GrokError) -> Self {
+        Error::Grok(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Grok(e.into())
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Error> {
+    match args.first().map(String::as_str) {
+        Some("keygen") => {
+            let [_, path] = args else {
+                return Err(Error::Usage("keygen takes a key file path".into()));
+            };
+            SessionKey::generate_keyfile(path)?;
+            println!("Wrote a new key to {}", path);
+            Ok(())
+        }
+        Some("encrypt") => encrypt(&args[1..]),
+        Some(other) => Err(Error::Usage(format!("unknown command {:?}", other))),
+        None => Err(Error::Usage("no command given".into())),
+    }
+}
+
+fn encrypt(args: &[String]) -> Result<(), Error> {
+    let mut session = None;
+    let mut out = None;
+    let mut keyfile = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next()
+                .map(PathBuf::from)
+                .ok_or_else(|| Error::Usage(format!("{} needs a path", flag)))
+        };
+        match arg.as_str() {
+            "--out" => out = Some(value("--out")?),
+            "--keyfile" => keyfile = Some(value("--keyfile")?),
+            flag if flag.starts_with("--") => {
+                return Err(Error::Usage(format!("unknown option {}", flag)))
+            }
+            path if session.is_none() => session = Some(PathBuf::from(path)),
+            extra => return Err(Error::Usage(format!("unexpected argument {:?}", extra))),
+        }
+    }
+
+    let session = session.ok_or_else(|| Error::Usage("encrypt takes a session file".into()))?;
+    let out = out.unwrap_or_else(|| session.clone());
+    let key = match keyfile {
+        Some(path) => SessionKey::from_keyfile(path)?,
+        None => SessionKey::from_passphrase(&passphrase()?),
+    };
+
+    // Keep a running client from writing the session meanwhile
+    let _lock = SessionLock::acquire(&session)?;
+    encrypt_session_file(&session, &out, key)?;
+    println!("Encrypted {} into {}", session.display(), out.display());
+    Ok(())
+}
+
+fn passphrase() -> Result<String, Error> {
+    if let Ok(passphrase) = std::env::var("GROK_SESSION_PASSPHRASE") {
+        if !passphrase.is_empty() {
+            return Ok(passphrase);
+        }
+    }
+
+    let passphrase = read_hidden("Passphrase: ")?;
+    if passphrase.is_empty() {
+        return Err(Error::Usage("the passphrase is empty".into()));
+    }
+    // A typo would lock the session away for good
+    if io::stdin().is_terminal() && read_hidden("Repeat passphrase: ")? != passphrase {
+        return Err(Error::Usage("the passphrases do not match".into()));
+    }
+    Ok(passphrase)
+}
+
+fn read_hidden(prompt: &str) -> Result<String, Error> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    let mut input = String::new();
+    {
+        let _hidden = hide_input()?;
+        io::stdin().lock().read_line(&mut input)?;
+    }
+    if io::stdin().is_terminal() {
+        // The newline was not echoed either
+        println!();
+    }
+    Ok(input.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Turns off terminal echo on stdin until dropped.
+#[cfg(unix)]
+struct HiddenInput(libc::termios);
+
+#[cfg(unix)]
+fn hide_input() -> Result<Option<HiddenInput>, Error> {
+    if !io::stdin().is_terminal() {
+        return Ok(None);
+    }
+    let mut termios = unsafe { std::mem::zeroed::<libc::termios>() };
+    if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut termios) } != 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    let saved = HiddenInput(termios);
+    termios.c_lflag &= !libc::ECHO;
+    if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios) } != 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(Some(saved))
+}
+
+#[cfg(unix)]
+impl Drop for HiddenInput {
+    fn drop(&mut self) {
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.0) };
+    }
+}
+
+/// Without a way to turn off echo, a passphrase is never typed in.
+#[cfg(not(unix))]
+fn hide_input() -> Result<Option<()>, Error> {
+    if io::stdin().is_terminal() {
+        return Err(Error::Usage(
+            "set GROK_SESSION_PASSPHRASE or pass --keyfile; the passphrase cannot be hidden here"
+                .into(),
+        ));
+    }
+    Ok(None)
+}
@@ -1,149 +1,724 @@
-use std::io;
-use grammers_client::{
-    Client, Config, InputMessage, Update,
-    types::{Chat, PackedChat},
-};
-use grammers_session::Session;
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, oneshot, watch, Mutex};
+use tokio::time::Instant;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::{
+    auth::{AuthPrompter, LoginMethod, QrLogin, TerminalPrompter},
     config::GrokConfig,
+    connection::{Backoff, ConnectionState, Reconnector},
+    correlation::{Correlator, ReplySender},
     error::GrokError,
+    events::{ClientEvent, Events},
+    handlers::{HandlerRegistry, MessageContext},
+    lifecycle::{ClientHandle, Phase},
     queue::{PriorityQueue, RequestPriority},
+    reply::{BotReply, RequestInfo},
+    request::{RequestHandle, RequestId, RequestStatus},
+    session::{FileSessionStore, SessionLock, SessionStore},
+    stream::ReplyStream,
+    throttle::{self, FloodWaitStats, RateLimit, Throttle},
+    transport::{
+        GrammersTransport, OutgoingMessage, Peer, PeerKind, QrLoginStep, SignInOutcome,
+        TelegramTransport, TransportUpdate,
+    },
 };
 
 pub struct GrokClient {
-    client: Client,
+    pub(crate) transport: Arc<dyn TelegramTransport>,
     queue: Arc<Mutex<PriorityQueue>>,
-    bot: PackedChat,
-    bot_id: i64,
+    correlator: Arc<Correlator>,
+    pub(crate) handlers: Arc<HandlerRegistry>,
+    accepting: Arc<AtomicBool>,
+    pub(crate) bot: Peer,
+    pub(crate) state: Arc<watch::Sender<ConnectionState>>,
+    pub(crate) events: Events,
+    backoff: Backoff,
+    flood_waits: Arc<std::sync::Mutex<FloodWaitStats>>,
+    rate_limit: RateLimit,
+    priority_rate_limits: HashMap<RequestPriority, RateLimit>,
+    session_save_interval: Option<Duration>,
+    pub(crate) response_timeout: Duration,
+    stream_quiet_period: Duration,
+    stream_final_marker: Option<String>,
+    /// Held while the client or a handle from it lives.
+    session_lock: Option<Arc<SessionLock>>,
 }
 
 impl GrokClient {
+    /// Connects to Telegram, asking for sign-in details on the terminal
+    /// if needed. Use [`GrokClient::builder`] to choose how instead.
+    ///
+    /// Fails with [`GrokError::SessionLocked`] if another client is using
+    /// `config.session_path`.
     pub async fn new(config: GrokConfig) -> Result<Self, GrokError> {
-        let session = Session::load_file_or_create(&config.session_path)
-            .map_err(|e| GrokError::Session(e.to_string()))?;
-
-        let client = Client::connect(Config {
-            session,
-            api_id: config.api_id,
-            api_hash: config.api_hash.clone(),
-            params: Default::default(),
-        })
-            .await
-            .map_err(|e| GrokError::Connection(e.to_string()))?;
+        Self::builder(config).build().await
+    }
+
+    /// Like [`GrokClient::new`], but talks to Telegram through `transport`.
+    pub async fn with_transport(
+        config: GrokConfig,
+        transport: impl TelegramTransport,
+    ) -> Result<Self, GrokError> {
+        Self::builder(config).transport(transport).build().await
+    }
+
+    pub fn builder(config: GrokConfig) -> GrokClientBuilder {
+        GrokClientBuilder {
+            config,
+            transport: None,
+            prompter: None,
+            login_method: LoginMethod::default(),
+            session_store: None,
+        }
+    }
+
+    async fn connect(builder: GrokClientBuilder) -> Result<Self, GrokError> {
+        let GrokClientBuilder {
+            mut config,
+            transport,
+            prompter,
+            login_method,
+            session_store,
+        } = builder;
+        config.validate()?;
+        // A given transport keeps its own session; ours keeps it in the store
+        let session_file = match (&transport, &session_store) {
+            (None, None) => Some(config.session_path.as_path()),
+            (None, Some(store)) => store.file_path(),
+            (Some(_), _) => None,
+        };
+        let session_lock = match session_file {
+            Some(path) => Some(Arc::new(SessionLock::acquire(path)?)),
+            None => None,
+        };
+        // Trusted bot ids live next to the session, wherever it is kept
+        let trust_store: Arc<dyn SessionStore> = match &session_store {
+            Some(store) => store.clone(),
+            None => Arc::new(FileSessionStore::new(&config.session_path)),
+        };
+        let transport = transport.unwrap_or_else(|| match session_store {
+            Some(store) => Arc::new(GrammersTransport::with_shared_store(&config, store)),
+            None => Arc::new(GrammersTransport::new(&config)),
+        });
+        let (state, _) = watch::channel(ConnectionState::Connecting);
+        transport.connect().await?;
 
-        if !client.is_authorized().await? {
-            Self::authorize(&client).await?;
+        if !transport.is_authorized().await? {
+            let prompter = prompter.unwrap_or_else(|| Arc::new(TerminalPrompter));
+            match login_method {
+                LoginMethod::Code => Self::authorize(transport.as_ref(), prompter.as_ref()).await?,
+                LoginMethod::Qr => Self::authorize_qr(transport.as_ref(), prompter.as_ref()).await?,
+            }
         }
 
-        let (bot, bot_id) = Self::resolve_bot(&client, &config.bot_username).await?;
+        let bot = Self::resolve_bot(transport.as_ref(), &config, trust_store.as_ref()).await?;
+        state.send_replace(ConnectionState::Connected);
 
         Ok(Self {
-            client: client.clone(),
+            transport,
             queue: Arc::new(Mutex::new(PriorityQueue::new())),
+            correlator: Arc::new(Correlator::new()),
+            handlers: Arc::new(HandlerRegistry::default()),
+            accepting: Arc::new(AtomicBool::new(true)),
             bot,
-            bot_id,
+            state: Arc::new(state),
+            events: Events::new(),
+            backoff: Backoff::new(
+                Duration::from_millis(config.reconnect_initial_delay_ms),
+                Duration::from_millis(config.reconnect_max_delay_ms),
+            ),
+            flood_waits: Arc::default(),
+            rate_limit: config.rate_limit,
+            priority_rate_limits: config.priority_rate_limits,
+            session_save_interval: match config.session_save_interval_secs {
+                0 => None,
+                secs => Some(Duration::from_secs(secs)),
+            },
+            response_timeout: Duration::from_secs(config.response_timeout),
+            stream_quiet_period: Duration::from_millis(config.stream_quiet_period_ms),
+            stream_final_marker: config.stream_final_marker,
+            session_lock,
         })
     }
 
-    pub async fn send(&self, text: &str, priority: RequestPriority) -> Result<(), GrokError> {
-        let message = InputMessage::text(text);
-        let mut queue = self.queue.lock().await;
-        queue.push(message, priority);
-        Ok(())
+    /// Follows the connection as it drops and comes back.
+    pub fn connection_state(&self) -> watch::Receiver<ConnectionState> {
+        self.state.subscribe()
     }
 
-    pub fn start(&self) {
-        let client = self.client.clone();
-        let queue = self.queue.clone();
-        let bot = self.bot.clone();
-        let bot_id = self.bot_id;
+    /// How often Telegram has throttled the sender so far.
+    pub fn flood_waits(&self) -> FloodWaitStats {
+        *self.flood_waits.lock().unwrap()
+    }
 
-        // Message sender
-        tokio::spawn(async move {
-            loop {
-                let msg = {
-                    let mut queue = queue.lock().await;
-                    queue.pop()
-                };
+    /// Queues a message for the bot: text, or media with a caption built
+    /// with [`OutgoingMessage::with_media`]. The handle follows it until it
+    /// is sent.
+    pub async fn send(
+        &self,
+        message: impl Into<OutgoingMessage>,
+        priority: RequestPriority,
+    ) -> Result<RequestHandle, GrokError> {
+        self.ensure_accepting()?;
+        let message = message.into();
+        let text = message.text.clone();
+        let (id, tracker) = self.queue.lock().await.push_tracked(message, priority);
+        self.emit_queued(id, text, priority);
+        Ok(RequestHandle::new(
+            id,
+            tracker,
+            self.queue.clone(),
+            None,
+            self.response_timeout,
+        ))
+    }
 
-                if let Some((message, priority)) = msg {
-                    match client.send_message(bot.clone(), message).await {
-                        Ok(_) => log::info!("Sent (priority: {:?})", priority),
-                        Err(e) => log::error!("Send error: {}", e),
-                    }
-                }
-                tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    /// Queues `message` expecting a reply. The handle follows it until it
+    /// is answered and hands over the reply.
+    pub async fn request(
+        &self,
+        message: impl Into<OutgoingMessage>,
+        priority: RequestPriority,
+    ) -> Result<RequestHandle, GrokError> {
+        self.ensure_accepting()?;
+        let message = message.into();
+        let text = message.text.clone();
+        let (tx, rx) = oneshot::channel();
+        let (id, tracker) = self
+            .queue
+            .lock()
+            .await
+            .push_request(message, priority, ReplySender::Once(tx));
+        self.emit_queued(id, text, priority);
+        Ok(RequestHandle::new(
+            id,
+            tracker,
+            self.queue.clone(),
+            Some(rx),
+            self.response_timeout,
+        ))
+    }
+
+    /// Queues `message` and waits for the bot's reply to it.
+    ///
+    /// Fails with [`GrokError::Timeout`] if no reply arrives within
+    /// `GrokConfig::response_timeout` seconds of the message being sent.
+    /// Dropping the future before the message is sent takes it off the
+    /// queue. Requires [`GrokClient::start`].
+    pub async fn ask(
+        &self,
+        message: impl Into<OutgoingMessage>,
+        priority: RequestPriority,
+    ) -> Result<BotReply, GrokError> {
+        self.request(message, priority)
+            .await?
+            .cancel_on_drop()
+            .reply()
+            .await
+    }
+
+    /// Queues `message` and follows the bot's reply as it edits it.
+    ///
+    /// The stream ends after the bot has been quiet for
+    /// `GrokConfig::stream_quiet_period_ms` or once the text ends with
+    /// `GrokConfig::stream_final_marker`. Fails with [`GrokError::Timeout`]
+    /// if the bot does not start replying within
+    /// `GrokConfig::response_timeout` seconds of the message being sent.
+    /// Dropping the stream before the message is sent takes it off the
+    /// queue.
+    pub async fn ask_stream(
+        &self,
+        message: impl Into<OutgoingMessage>,
+        priority: RequestPriority,
+    ) -> Result<ReplyStream, GrokError> {
+        self.ensure_accepting()?;
+        let message = message.into();
+        let text = message.text.clone();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (id, tracker) = self
+            .queue
+            .lock()
+            .await
+            .push_request(message, priority, ReplySender::Stream(tx));
+        self.emit_queued(id, text, priority);
+        let request = RequestHandle::new(
+            id,
+            tracker,
+            self.queue.clone(),
+            None,
+            self.response_timeout,
+        )
+        .cancel_on_drop();
+
+        Ok(ReplyStream::spawn(
+            request,
+            rx,
+            self.response_timeout,
+            self.stream_quiet_period,
+            self.stream_final_marker.clone(),
+        ))
+    }
+
+    /// Spawns the sender, listener and handler tasks.
+    pub fn start(&self) -> ClientHandle {
+        let (phase_tx, phase_rx) = watch::channel(Phase::Running);
+        let (dispatch_tx, dispatch_rx) = mpsc::unbounded_channel();
+
+        let sender = tokio::spawn(run_sender(
+            self.transport.clone(),
+            self.queue.clone(),
+            self.correlator.clone(),
+            self.bot,
+            phase_rx.clone(),
+            self.state.subscribe(),
+            Throttle::new(
+                self.rate_limit,
+                &self.priority_rate_limits,
+                self.flood_waits.clone(),
+            ),
+            self.events.clone(),
+        ));
+        let listener = tokio::spawn(run_listener(
+            Reconnector {
+                transport: self.transport.clone(),
+                state: self.state.clone(),
+                events: self.events.clone(),
+                backoff: self.backoff.clone(),
+            },
+            self.queue.clone(),
+            self.accepting.clone(),
+            self.correlator.clone(),
+            self.bot.id,
+            dispatch_tx,
+            phase_rx.clone(),
+        ));
+        let dispatcher = tokio::spawn(run_dispatcher(self.handlers.clone(), dispatch_rx));
+
+        let mut tasks = vec![
+            ("sender", sender),
+            ("listener", listener),
+            ("dispatcher", dispatcher),
+        ];
+        if let Some(interval) = self.session_save_interval {
+            let saver = run_session_saver(self.transport.clone(), interval, phase_rx);
+            tasks.push(("session", tokio::spawn(saver)));
+        }
+
+        ClientHandle {
+            phase: phase_tx,
+            accepting: self.accepting.clone(),
+            queue: self.queue.clone(),
+            correlator: self.correlator.clone(),
+            transport: self.transport.clone(),
+            tasks,
+            session_lock: self.session_lock.clone(),
+        }
+    }
+
+    fn emit_queued(&self, id: RequestId, text: String, priority: RequestPriority) {
+        self.events.emit(ClientEvent::Queued { id, priority, text });
+    }
+
+    fn ensure_accepting(&self) -> Result<(), GrokError> {
+        if self.accepting.load(Ordering::SeqCst) {
+            Ok(())
+        } else {
+            Err(GrokError::ShuttingDown)
+        }
+    }
+
+    /// Resolves `config.bot_username` and checks it is the bot we expect,
+    /// since usernames can change hands.
+    async fn resolve_bot(
+        transport: &dyn TelegramTransport,
+        config: &GrokConfig,
+        trust_store: &dyn SessionStore,
+    ) -> Result<Peer, GrokError> {
+        let username = &config.bot_username;
+        let peer = transport
+            .resolve_username(username)
+            .await?
+            .ok_or_else(|| GrokError::Authorization(format!("Bot {} not found", username)))?;
+
+        if peer.kind != PeerKind::Bot {
+            return Err(GrokError::Bot(format!("@{} is not a bot account", username)));
+        }
+
+        let expected = match config.bot_id {
+            Some(id) => Some(id),
+            None if config.trust_bot_on_first_use => trust_store.trusted_bot(username)?,
+            None => None,
+        };
+        match expected {
+            Some(expected) if expected != peer.id => Err(GrokError::BotIdentityMismatch {
+                username: username.clone(),
+                expected,
+                actual: peer.id,
+            }),
+            None if config.trust_bot_on_first_use => {
+                trust_store.trust_bot(username, peer.id)?;
+                log::info!("Trusting @{} as bot {} from now on", username, peer.id);
+                Ok(peer)
             }
-        });
+            _ => Ok(peer),
+        }
+    }
+
+    async fn authorize(
+        transport: &dyn TelegramTransport,
+        prompter: &dyn AuthPrompter,
+    ) -> Result<(), GrokError> {
+        let phone = prompter.phone().await?;
+        let token = transport.request_login_code(&phone).await?;
+        let code = prompter.code(token.delivery()).await?;
+        let outcome = transport.sign_in(&token, &code).await?;
+        Self::finish_sign_in(transport, prompter, outcome).await
+    }
+
+    /// Shows QR codes until one is scanned, refreshing each as it expires.
+    async fn authorize_qr(
+        transport: &dyn TelegramTransport,
+        prompter: &dyn AuthPrompter,
+    ) -> Result<(), GrokError> {
+        loop {
+            let (token, expires) = match transport.export_login_token().await? {
+                QrLoginStep::Token { token, expires } => (token, expires),
+                QrLoginStep::Done(outcome) => {
+                    return Self::finish_sign_in(transport, prompter, outcome).await
+                }
+            };
+            let qr = QrLogin::new(token, expires);
+            prompter.show_qr(&qr).await?;
 
-        // Message listener
-        let client = self.client.clone();
-        tokio::spawn(async move {
-            loop {
-                match client.next_update().await {
-                    Ok(Update::NewMessage(message)) => {
-                        if let Some(sender) = message.sender() {
-                            if sender.id() == bot_id {
-                                println!("\n[Bot]: {}", message.text());
-                            }
-                        }
+            // Exporting again after expiry also notices a scan whose update
+            // was missed
+            let scanned = async {
+                loop {
+                    if let TransportUpdate::LoginTokenAccepted = transport.next_update().await? {
+                        return Ok::<_, GrokError>(());
                     }
-                    Ok(_) => {}
-                    Err(e) => log::error!("Update error: {}", e),
                 }
+            };
+            if let Ok(result) = tokio::time::timeout(qr.expires_in(), scanned).await {
+                result?;
             }
-        });
+        }
     }
 
-    async fn resolve_bot(client: &Client, username: &str) -> Result<(PackedChat, i64), GrokError> {
-        let chat = client
-            .resolve_username(username)
-            .await?
-            .ok_or_else(|| GrokError::Authorization(format!("Bot {} not found", username)))?;
+    async fn finish_sign_in(
+        transport: &dyn TelegramTransport,
+        prompter: &dyn AuthPrompter,
+        outcome: SignInOutcome,
+    ) -> Result<(), GrokError> {
+        match outcome {
+            SignInOutcome::Authorized => {
+                transport.save_session()?;
+                log::info!("Authorization successful");
+                Ok(())
+            }
+            SignInOutcome::PasswordRequired(challenge) => {
+                let password = prompter.password(challenge.hint.as_deref()).await?;
+                transport.check_password(challenge, &password).await?;
+                transport.save_session()?;
+                log::info!("2FA authentication successful");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Configures how a [`GrokClient`] connects and signs in.
+pub struct GrokClientBuilder {
+    config: GrokConfig,
+    transport: Option<Arc<dyn TelegramTransport>>,
+    prompter: Option<Arc<dyn AuthPrompter>>,
+    login_method: LoginMethod,
+    session_store: Option<Arc<dyn SessionStore>>,
+}
+
+impl GrokClientBuilder {
+    /// Talks to Telegram through `transport` instead of [`GrammersTransport`].
+    pub fn transport(mut self, transport: impl TelegramTransport) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Asks `prompter` for sign-in details instead of the terminal.
+    pub fn prompter(mut self, prompter: impl AuthPrompter + 'static) -> Self {
+        self.prompter = Some(Arc::new(prompter));
+        self
+    }
+
+    /// Signs in with `method` if the session is not authorized. Asks for a
+    /// phone number and code by default.
+    pub fn login_method(mut self, method: LoginMethod) -> Self {
+        self.login_method = method;
+        self
+    }
+
+    /// Keeps the session in `store` instead of `GrokConfig::session_path`,
+    /// locking the store's file if it has one. A given transport keeps its
+    /// own session, but `store` still records bots trusted on first use.
+    pub fn session_store(mut self, store: impl SessionStore) -> Self {
+        self.session_store = Some(Arc::new(store));
+        self
+    }
+
+    pub async fn build(self) -> Result<GrokClient, GrokError> {
+        GrokClient::connect(self).await
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_sender(
+    transport: Arc<dyn TelegramTransport>,
+    queue: Arc<Mutex<PriorityQueue>>,
+    correlator: Arc<Correlator>,
+    bot: Peer,
+    mut phase: watch::Receiver<Phase>,
+    mut state: watch::Receiver<ConnectionState>,
+    mut throttle: Throttle,
+    events: Events,
+) {
+    let pushed = queue.lock().await.notifier();
+    loop {
+        let deadline = match *phase.borrow() {
+            Phase::Running => None,
+            Phase::Draining { deadline } => {
+                if Instant::now() >= deadline {
+                    break;
+                }
+                Some(deadline)
+            }
+            Phase::Stopped => break,
+        };
+
+        // Keep messages queued until the listener has reconnected
+        if *state.borrow() != ConnectionState::Connected {
+            tokio::select! {
+                _ = changed(&mut state) => {}
+                _ = changed(&mut phase) => {}
+                _ = sleep_until(deadline) => {}
+            }
+            continue;
+        }
+
+        let now = Instant::now();
+        let (item, next_ready) = {
+            let mut queue = queue.lock().await;
+            let item = queue.pop_item_where(|p| throttle.wait(p, now).is_zero());
+            let next_ready = match item {
+                Some(_) => None,
+                None => queue.priorities().into_iter().map(|p| throttle.wait(p, now)).min(),
+            };
+            (item, next_ready)
+        };
 
-        let bot_id = match &chat {
-            Chat::User(user) => user.id(),
-            _ => return Err(GrokError::Bot("Target is not a user bot".into())),
+        let Some(item) = item else {
+            if deadline.is_some() && next_ready.is_none() {
+                break;
+            }
+            // Nothing we may send yet: wait for a push, a token or shutdown
+            let wake = next_ready.map(|wait| now + wait);
+            let wake = match (wake, deadline) {
+                (Some(wake), Some(deadline)) => Some(wake.min(deadline)),
+                (wake, deadline) => wake.or(deadline),
+            };
+            tokio::select! {
+                _ = pushed.notified() => {}
+                _ = changed(&mut phase) => {}
+                _ = sleep_until(wake) => {}
+            }
+            continue;
         };
 
-        Ok((chat.pack(), bot_id))
+        // Cancelled while we were popping it
+        if !item.status.start_sending() {
+            continue;
+        }
+        throttle.take(item.priority);
+        let target = item.target.unwrap_or(bot);
+        match transport.send_message(target, item.message.clone()).await {
+            Ok(sent) => {
+                log::info!("Sent (priority: {:?})", item.priority);
+                events.emit(ClientEvent::Sent {
+                    id: item.id,
+                    message_id: sent.id,
+                    priority: item.priority,
+                    text: item.message.text.clone(),
+                });
+                item.status.set(RequestStatus::Sent { message_id: sent.id });
+                if let Some(reply) = item.reply {
+                    let request = RequestInfo {
+                        message_id: sent.id,
+                        text: item.message.text,
+                    };
+                    correlator.register(request, reply, item.status);
+                }
+            }
+            Err(GrokError::Connection(e)) => {
+                log::warn!("Send failed, will retry after reconnecting: {}", e);
+                item.status.set(RequestStatus::Queued);
+                queue.lock().await.requeue(item);
+            }
+            Err(GrokError::FloodWait(wait)) => {
+                // Retry the same message once Telegram lets us
+                log::warn!("Flood wait, pausing the queue for {:?}", wait);
+                events.emit(ClientEvent::FloodWait { wait });
+                item.status.set(RequestStatus::Queued);
+                queue.lock().await.requeue(item);
+                let until = Instant::now() + wait;
+                throttle.record_flood_wait(wait, until);
+                throttle::pause(until, &mut phase).await;
+                throttle.flood_wait_over();
+            }
+            Err(e) => {
+                log::error!("Send error: {}", e);
+                item.status.set(RequestStatus::Failed(e.to_string()));
+                events.emit(ClientEvent::SendFailed {
+                    id: item.id,
+                    priority: item.priority,
+                    text: item.message.text,
+                    error: e.to_string(),
+                });
+                if let Some(reply) = item.reply {
+                    reply.deliver(Err(e));
+                }
+            }
+        }
+    }
+}
+
+/// Like `watch::Receiver::changed`, but never resolves once the sender is
+/// gone, so a dropped [`ClientHandle`] cannot make a `select!` spin.
+async fn changed<T>(rx: &mut watch::Receiver<T>) {
+    if rx.changed().await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+async fn sleep_until(wake: Option<Instant>) {
+    match wake {
+        Some(wake) => tokio::time::sleep_until(wake).await,
+        None => std::future::pending().await,
     }
+}
 
-    async fn authorize(client: &Client) -> Result<(), GrokError> {
-        use grammers_client::SignInError;
+async fn run_listener(
+    mut reconnector: Reconnector,
+    queue: Arc<Mutex<PriorityQueue>>,
+    accepting: Arc<AtomicBool>,
+    correlator: Arc<Correlator>,
+    bot_id: i64,
+    dispatch: mpsc::UnboundedSender<MessageContext>,
+    mut phase: watch::Receiver<Phase>,
+) {
+    let stopped = async {
+        // A dropped handle means nobody can stop us any more
+        if phase.wait_for(|p| *p == Phase::Stopped).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    };
+    tokio::select! {
+        _ = stopped => {}
+        _ = listen(&mut reconnector, &queue, &accepting, &correlator, bot_id, &dispatch) => {}
+    }
+}
 
-        println!("Enter phone number (e.g. +1234567890):");
-        let phone = read_input()?;
+async fn listen(
+    reconnector: &mut Reconnector,
+    queue: &Arc<Mutex<PriorityQueue>>,
+    accepting: &Arc<AtomicBool>,
+    correlator: &Correlator,
+    bot_id: i64,
+    dispatch: &mpsc::UnboundedSender<MessageContext>,
+) {
+    loop {
+        let (message, edited) = match reconnector.transport.next_update().await {
+            Ok(TransportUpdate::NewMessage(message)) => (message, false),
+            Ok(TransportUpdate::MessageEdited(message)) => (message, true),
+            Ok(TransportUpdate::LoginTokenAccepted | TransportUpdate::Other) => continue,
+            Err(GrokError::Connection(e)) => {
+                log::warn!("Connection lost: {}", e);
+                if !reconnector.reconnect().await {
+                    // Nothing more to listen to until someone signs in again
+                    std::future::pending::<()>().await;
+                }
+                continue;
+            }
+            Err(e) => {
+                log::error!("Update error: {}", e);
+                tokio::time::sleep(reconnector.backoff.next_delay()).await;
+                continue;
+            }
+        };
+        reconnector.backoff.reset();
+        if message.outgoing {
+            continue;
+        }
 
-        let token = client.request_login_code(&phone).await?;
+        let from_bot = message.sender_id == Some(bot_id) && message.chat.id == bot_id;
+        let request = if from_bot {
+            let mut reply = BotReply::from_incoming(message.clone(), edited);
+            let request = if edited {
+                correlator.resolve_edit(reply.clone())
+            } else {
+                correlator.resolve(reply.clone())
+            };
+            reply.request_message_id = request.as_ref().map(|r| r.message_id);
+            reconnector.events.emit(if edited {
+                ClientEvent::ReplyEdited(reply)
+            } else {
+                ClientEvent::ReplyReceived(reply)
+            });
+            request
+        } else {
+            None
+        };
 
-        println!("Enter Telegram code:");
-        let code = read_input()?;
+        let ctx = MessageContext::new(
+            message,
+            edited,
+            from_bot,
+            request,
+            queue.clone(),
+            accepting.clone(),
+            reconnector.events.clone(),
+        );
+        let _ = dispatch.send(ctx);
+    }
+}
 
-        match client.sign_in(&token, &code).await {
-            Ok(_) => {
-                client.session().save_to_file("session.session")?;
-                println!("Authorization successful!");
-                Ok(())
+async fn run_session_saver(
+    transport: Arc<dyn TelegramTransport>,
+    interval: Duration,
+    mut phase: watch::Receiver<Phase>,
+) {
+    loop {
+        tokio::select! {
+            _ = changed(&mut phase) => {
+                if *phase.borrow() == Phase::Stopped {
+                    break;
+                }
             }
-            Err(SignInError::PasswordRequired(token)) => {
-                println!("Enter 2FA password:");
-                let password = read_input()?;
-                client.check_password(token, &password).await?;
-                client.session().save_to_file("session.session")?;
-                println!("2FA authentication successful!");
-                Ok(())
+            _ = tokio::time::sleep(interval) => {
+                if let Err(e) = transport.save_session() {
+                    log::error!("Failed to save session: {}", e);
+                }
             }
-            Err(e) => Err(e.into()),
         }
     }
 }
 
-fn read_input() -> Result<String, GrokError> {
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-    Ok(input.trim().to_string())
+// Runs handlers off the listener task so slow handlers never hold it up.
+async fn run_dispatcher(
+    handlers: Arc<HandlerRegistry>,
+    mut contexts: mpsc::UnboundedReceiver<MessageContext>,
+) {
+    while let Some(ctx) = contexts.recv().await {
+        handlers.dispatch(&ctx).await;
+    }
 }
\ No newline at end of file
@@ -1,13 +1,81 @@
 use serde::Deserialize;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::{error::ConfigError, queue::RequestPriority, throttle::RateLimit};
+
+mod env;
+mod loader;
+mod validate;
+
+pub use loader::ConfigLoader;
+pub use validate::ConfigProblem;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct GrokConfig {
     pub api_id: i32,
     pub api_hash: String,
     pub bot_username: String,
+    /// The bot's user id. If set, connecting fails with
+    /// [`GrokError::BotIdentityMismatch`](crate::GrokError::BotIdentityMismatch)
+    /// when `bot_username` belongs to another account.
+    #[serde(default)]
+    pub bot_id: Option<i64>,
+    /// Without `bot_id`, remember the id `bot_username` resolves to on the
+    /// first connect, in the session store, and require it from then on.
+    #[serde(default)]
+    pub trust_bot_on_first_use: bool,
+    #[serde(default = "default_session_path")]
     pub session_path: PathBuf,
+    #[serde(default = "default_response_timeout")]
     pub response_timeout: u64,
+    /// A streamed reply is complete once the bot stops editing it for this
+    /// many milliseconds.
+    #[serde(default = "default_stream_quiet_period_ms")]
+    pub stream_quiet_period_ms: u64,
+    /// A streamed reply is complete as soon as its text ends with this marker.
+    #[serde(default)]
+    pub stream_final_marker: Option<String>,
+    /// First delay before reconnecting after the connection drops, doubled
+    /// (with jitter) on every failed attempt.
+    #[serde(default = "default_reconnect_initial_delay_ms")]
+    pub reconnect_initial_delay_ms: u64,
+    #[serde(default = "default_reconnect_max_delay_ms")]
+    pub reconnect_max_delay_ms: u64,
+    /// How often a started client saves its session, in seconds; 0 saves
+    /// only after signing in and on shutdown.
+    #[serde(default = "default_session_save_interval_secs")]
+    pub session_save_interval_secs: u64,
+    /// How fast the queue may send, across all priorities.
+    #[serde(default)]
+    pub rate_limit: RateLimit,
+    /// Extra limits for individual priorities, on top of `rate_limit`.
+    #[serde(default)]
+    pub priority_rate_limits: HashMap<RequestPriority, RateLimit>,
+}
+
+fn default_session_path() -> PathBuf {
+    PathBuf::from("session.session")
+}
+
+fn default_response_timeout() -> u64 {
+    30
+}
+
+fn default_session_save_interval_secs() -> u64 {
+    300
+}
+
+fn default_stream_quiet_period_ms() -> u64 {
+    3000
+}
+
+fn default_reconnect_initial_delay_ms() -> u64 {
+    500
+}
+
+fn default_reconnect_max_delay_ms() -> u64 {
+    60_000
 }
 
 impl GrokConfig {
@@ -21,8 +89,54 @@ impl GrokConfig {
             api_id,
             api_hash: api_hash.into(),
             bot_username: bot_username.into(),
+            bot_id: None,
+            trust_bot_on_first_use: false,
             session_path: session_path.into(),
-            response_timeout: 30,
+            response_timeout: default_response_timeout(),
+            stream_quiet_period_ms: default_stream_quiet_period_ms(),
+            stream_final_marker: None,
+            reconnect_initial_delay_ms: default_reconnect_initial_delay_ms(),
+            reconnect_max_delay_ms: default_reconnect_max_delay_ms(),
+            session_save_interval_secs: default_session_save_interval_secs(),
+            rate_limit: RateLimit::default(),
+            priority_rate_limits: HashMap::new(),
         }
     }
+}
+
+impl GrokConfig {
+    /// Reads the config from environment variables.
+    ///
+    /// `API_ID`, `API_HASH` and `BOT_USERNAME` are required. `SESSION_PATH`
+    /// defaults to `session.session`, `RESPONSE_TIMEOUT` is in seconds and
+    /// `QUEUE_INTERVAL` is the minimum gap between messages in milliseconds.
+    /// Other settings use their field name with a `GROK_` prefix, e.g.
+    /// `GROK_STREAM_QUIET_PERIOD_MS`, `GROK_RATE_LIMIT_PER_SECOND` or
+    /// `GROK_RATE_LIMIT_LOW_BURST`.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::loader().env().load()
+    }
+
+    /// Loads the `.env` file at `path` into the environment, without
+    /// overriding variables already set, then calls [`GrokConfig::from_env`].
+    pub fn from_dotenv(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Self::loader().dotenv(path).load()
+    }
+
+    /// Like [`GrokConfig::from_env`], but reads `vars` instead of the
+    /// environment. Later pairs override earlier ones.
+    pub fn from_vars<I, K, V>(vars: I) -> Result<Self, ConfigError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        Self::loader().vars(vars).load()
+    }
+
+    /// Layers defaults, config files, the environment and explicit
+    /// overrides, in that order.
+    pub fn loader() -> ConfigLoader {
+        ConfigLoader::default()
+    }
 }
\ No newline at end of file
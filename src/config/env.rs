@@ -0,0 +1,236 @@
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::{error::ConfigError, throttle::RateLimit};
+
+/// Prefix for environment variables; `GROK_RESPONSE_TIMEOUT` wins over
+/// `RESPONSE_TIMEOUT`.
+pub(super) const PREFIX: &str = "GROK_";
+
+/// Variable infix and serde name of each priority.
+const PRIORITIES: [(&str, &str); 4] = [
+    ("EMERGENCY", "Emergency"),
+    ("HIGH", "High"),
+    ("NORMAL", "Normal"),
+    ("LOW", "Low"),
+];
+
+/// Environment variables, read as one config layer.
+pub(super) struct Vars {
+    vars: HashMap<String, String>,
+    /// Values that are not valid UTF-8, lossily converted. Only an error if
+    /// we read them.
+    garbled: HashMap<String, String>,
+}
+
+impl Vars {
+    pub fn new(vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        Self {
+            vars: vars.into_iter().collect(),
+            garbled: HashMap::new(),
+        }
+    }
+
+    /// The process environment. Unlike `std::env::vars`, does not panic on
+    /// variables that are not valid UTF-8.
+    pub fn from_env() -> Self {
+        let mut vars = Self::new([]);
+        for (key, value) in std::env::vars_os() {
+            // Not a name we could be looking for
+            let Ok(key) = key.into_string() else { continue };
+            match value.into_string() {
+                Ok(value) => vars.vars.insert(key, value),
+                Err(value) => vars.garbled.insert(key, value.to_string_lossy().into_owned()),
+            };
+        }
+        vars
+    }
+
+    /// The value of `GROK_<key>`, ignoring a bare `<key>`.
+    pub fn prefixed(&self, key: &str) -> Result<Option<&str>, ConfigError> {
+        let name = format!("{PREFIX}{key}");
+        self.check_utf8(&name)?;
+        let Some(value) = self.vars.get(&name) else {
+            return Ok(None);
+        };
+        let value = value.trim();
+        Ok((!value.is_empty()).then_some(value))
+    }
+
+    /// Turns the variables into a layer over `base`, the merged layers
+    /// below them.
+    pub fn overlay(&self, base: &Map<String, Value>) -> Result<Map<String, Value>, ConfigError> {
+        let mut layer = Map::new();
+
+        self.put::<i32>(&mut layer, &["api_id"], "API_ID")?;
+        self.put::<String>(&mut layer, &["api_hash"], "API_HASH")?;
+        self.put::<String>(&mut layer, &["bot_username"], "BOT_USERNAME")?;
+        self.put::<i64>(&mut layer, &["bot_id"], "BOT_ID")?;
+        self.put::<bool>(
+            &mut layer,
+            &["trust_bot_on_first_use"],
+            "TRUST_BOT_ON_FIRST_USE",
+        )?;
+        self.put::<String>(&mut layer, &["session_path"], "SESSION_PATH")?;
+        self.put::<u64>(&mut layer, &["response_timeout"], "RESPONSE_TIMEOUT")?;
+        if let Some(interval) = self.parse::<u64>("QUEUE_INTERVAL")? {
+            if interval == 0 {
+                return Err(self.invalid("QUEUE_INTERVAL", "must be at least 1 millisecond"));
+            }
+            let limit = RateLimit::every(Duration::from_millis(interval));
+            let value = json!({ "per_second": limit.per_second, "burst": limit.burst });
+            insert(&mut layer, &["rate_limit"], value);
+        }
+
+        self.put::<u64>(
+            &mut layer,
+            &["stream_quiet_period_ms"],
+            "STREAM_QUIET_PERIOD_MS",
+        )?;
+        self.put::<String>(&mut layer, &["stream_final_marker"], "STREAM_FINAL_MARKER")?;
+        self.put::<u64>(
+            &mut layer,
+            &["reconnect_initial_delay_ms"],
+            "RECONNECT_INITIAL_DELAY_MS",
+        )?;
+        self.put::<u64>(
+            &mut layer,
+            &["reconnect_max_delay_ms"],
+            "RECONNECT_MAX_DELAY_MS",
+        )?;
+        self.put::<u64>(
+            &mut layer,
+            &["session_save_interval_secs"],
+            "SESSION_SAVE_INTERVAL_SECS",
+        )?;
+        self.put_rate(
+            &mut layer,
+            &["rate_limit", "per_second"],
+            "RATE_LIMIT_PER_SECOND",
+        )?;
+        self.put::<u32>(&mut layer, &["rate_limit", "burst"], "RATE_LIMIT_BURST")?;
+
+        for (name, priority) in PRIORITIES {
+            let path = ["priority_rate_limits", priority];
+            let per_second = format!("RATE_LIMIT_{name}_PER_SECOND");
+            let burst = format!("RATE_LIMIT_{name}_BURST");
+            let has_rate = self.put_rate(
+                &mut layer,
+                &[&path[..], &["per_second"]].concat(),
+                &per_second,
+            )?;
+            let has_burst =
+                self.put::<u32>(&mut layer, &[&path[..], &["burst"]].concat(), &burst)?;
+
+            let below = base
+                .get(path[0])
+                .and_then(|limits| limits.get(priority))
+                .is_some_and(|limit| limit.get("per_second").is_some());
+            if has_burst && !has_rate && !below {
+                return Err(self.invalid(&burst, &format!("{PREFIX}{per_second} is not set")));
+            }
+        }
+
+        Ok(layer)
+    }
+
+    fn lookup(&self, key: &str) -> Option<(String, &str)> {
+        [format!("{PREFIX}{key}"), key.to_string()]
+            .into_iter()
+            .find_map(|name| {
+                let value = self.vars.get(&name)?.trim();
+                (!value.is_empty()).then_some((name, value))
+            })
+    }
+
+    fn check_utf8(&self, name: &str) -> Result<(), ConfigError> {
+        match self.garbled.get(name) {
+            Some(value) => Err(ConfigError::Invalid {
+                key: name.to_string(),
+                value: value.clone(),
+                reason: "not valid UTF-8".into(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn parse<T>(&self, key: &str) -> Result<Option<T>, ConfigError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.check_utf8(&format!("{PREFIX}{key}"))?;
+        self.check_utf8(key)?;
+        let Some((name, value)) = self.lookup(key) else {
+            return Ok(None);
+        };
+        value
+            .parse()
+            .map(Some)
+            .map_err(|e: T::Err| ConfigError::Invalid {
+                key: name,
+                value: value.to_string(),
+                reason: e.to_string(),
+            })
+    }
+
+    /// Sets `path` in `layer` if `key` is set. Returns whether it was.
+    fn put<T>(
+        &self,
+        layer: &mut Map<String, Value>,
+        path: &[&str],
+        key: &str,
+    ) -> Result<bool, ConfigError>
+    where
+        T: FromStr + Serialize,
+        T::Err: Display,
+    {
+        let Some(value) = self.parse::<T>(key)? else {
+            return Ok(false);
+        };
+        insert(layer, path, json!(value));
+        Ok(true)
+    }
+
+    fn put_rate(
+        &self,
+        layer: &mut Map<String, Value>,
+        path: &[&str],
+        key: &str,
+    ) -> Result<bool, ConfigError> {
+        let Some(rate) = self.parse::<f64>(key)? else {
+            return Ok(false);
+        };
+        if !(rate.is_finite() && rate > 0.0) {
+            return Err(self.invalid(key, "must be a positive number"));
+        }
+        insert(layer, path, json!(rate));
+        Ok(true)
+    }
+
+    fn invalid(&self, key: &str, reason: &str) -> ConfigError {
+        let (name, value) = self.lookup(key).unwrap_or_else(|| (key.to_string(), ""));
+        ConfigError::Invalid {
+            key: name,
+            value: value.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+fn insert(map: &mut Map<String, Value>, path: &[&str], value: Value) {
+    let (last, parents) = path.split_last().expect("empty config path");
+    let mut map = map;
+    for key in parents {
+        let entry = map.entry(*key).or_insert_with(|| Value::Object(Map::new()));
+        if !entry.is_object() {
+            *entry = Value::Object(Map::new());
+        }
+        map = entry.as_object_mut().unwrap();
+    }
+    map.insert(last.to_string(), value);
+}
@@ -0,0 +1,197 @@
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+use super::{env::Vars, GrokConfig};
+use crate::error::ConfigError;
+
+/// Fields with no default, with the variable that sets each.
+const REQUIRED: [(&str, &str); 3] = [
+    ("api_id", "API_ID"),
+    ("api_hash", "API_HASH"),
+    ("bot_username", "BOT_USERNAME"),
+];
+
+enum EnvSource {
+    Process,
+    Dotenv(PathBuf),
+    Vars(Vec<(String, String)>),
+}
+
+type Override = Box<dyn FnOnce(&mut GrokConfig) + Send>;
+
+/// Builds a [`GrokConfig`] from layers: defaults, then config files in the
+/// order added, then the environment, then explicit overrides.
+///
+/// Files are TOML or JSON, picked by extension. Top-level keys are the
+/// [`GrokConfig`] fields; a `[profile.<name>]` table is layered over them
+/// when that profile is selected:
+///
+/// ```toml
+/// api_id = 12345
+/// api_hash = "..."
+/// bot_username = "GrokAI"
+///
+/// [profile.work]
+/// session_path = "work.session"
+///
+/// [profile.test]
+/// bot_username = "GrokTestBot"
+/// response_timeout = 5
+/// ```
+#[derive(Default)]
+pub struct ConfigLoader {
+    files: Vec<(PathBuf, bool)>,
+    profile: Option<String>,
+    env: Option<EnvSource>,
+    overrides: Vec<Override>,
+}
+
+impl ConfigLoader {
+    /// Adds a config file, which must exist.
+    pub fn file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.files.push((path.into(), true));
+        self
+    }
+
+    /// Adds a config file that is skipped if it does not exist.
+    pub fn optional_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.files.push((path.into(), false));
+        self
+    }
+
+    /// Selects `[profile.<name>]` from the files. Without this, the
+    /// `GROK_PROFILE` variable selects one if the environment is read.
+    pub fn profile(mut self, name: impl Into<String>) -> Self {
+        self.profile = Some(name.into());
+        self
+    }
+
+    /// Reads the environment, as [`GrokConfig::from_env`] does.
+    pub fn env(mut self) -> Self {
+        self.env = Some(EnvSource::Process);
+        self
+    }
+
+    /// Loads a `.env` file into the environment, then reads the environment.
+    pub fn dotenv(mut self, path: impl AsRef<Path>) -> Self {
+        self.env = Some(EnvSource::Dotenv(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Reads `vars` in place of the environment. Later pairs override
+    /// earlier ones.
+    pub fn vars<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let vars = vars
+            .into_iter()
+            .map(|(k, v)| (k.into(), v.into()))
+            .collect();
+        self.env = Some(EnvSource::Vars(vars));
+        self
+    }
+
+    /// Changes the config after every other layer.
+    pub fn set(mut self, f: impl FnOnce(&mut GrokConfig) + Send + 'static) -> Self {
+        self.overrides.push(Box::new(f));
+        self
+    }
+
+    pub fn load(self) -> Result<GrokConfig, ConfigError> {
+        let vars = match self.env {
+            None => None,
+            Some(EnvSource::Process) => Some(Vars::from_env()),
+            Some(EnvSource::Dotenv(path)) => {
+                dotenv::from_path(&path).map_err(|e| ConfigError::File {
+                    path,
+                    reason: e.to_string(),
+                })?;
+                Some(Vars::from_env())
+            }
+            Some(EnvSource::Vars(vars)) => Some(Vars::new(vars)),
+        };
+        let profile = match self.profile {
+            Some(name) => Some(name),
+            None if self.files.is_empty() => None,
+            None => match &vars {
+                Some(vars) => vars.prefixed("PROFILE")?.map(str::to_string),
+                None => None,
+            },
+        };
+
+        let mut merged = Map::new();
+        let mut profile_found = false;
+        for (path, required) in &self.files {
+            if !required && !path.exists() {
+                continue;
+            }
+            let mut layer = read_file(path)?;
+            let profiles = layer.remove("profile");
+            merge(&mut merged, layer);
+
+            let Some(name) = &profile else { continue };
+            if let Some(Value::Object(selected)) = profiles.as_ref().and_then(|p| p.get(name)) {
+                merge(&mut merged, selected.clone());
+                profile_found = true;
+            }
+        }
+        if let Some(name) = profile {
+            if !profile_found {
+                return Err(ConfigError::UnknownProfile(name));
+            }
+        }
+
+        if let Some(vars) = &vars {
+            let layer = vars.overlay(&merged)?;
+            merge(&mut merged, layer);
+        }
+
+        for (field, var) in REQUIRED {
+            if !merged.contains_key(field) {
+                let key = if vars.is_some() { var } else { field };
+                return Err(ConfigError::Missing { key });
+            }
+        }
+
+        let mut config: GrokConfig = serde_json::from_value(Value::Object(merged))
+            .map_err(|e| ConfigError::Malformed(e.to_string()))?;
+        for f in self.overrides {
+            f(&mut config);
+        }
+        Ok(config)
+    }
+}
+
+fn read_file(path: &Path) -> Result<Map<String, Value>, ConfigError> {
+    let error = |reason: String| ConfigError::File {
+        path: path.to_path_buf(),
+        reason,
+    };
+    let text = std::fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+
+    let value = match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::from_str::<Value>(&text).map_err(|e| error(e.to_string()))?,
+        Some("json") => serde_json::from_str::<Value>(&text).map_err(|e| error(e.to_string()))?,
+        _ => return Err(error("expected a .toml or .json file".into())),
+    };
+    match value {
+        Value::Object(map) => Ok(map),
+        _ => Err(error("expected a table of settings".into())),
+    }
+}
+
+/// Merges `layer` into `base`, recursing into tables so a layer only
+/// replaces the settings it names.
+fn merge(base: &mut Map<String, Value>, layer: Map<String, Value>) {
+    for (key, value) in layer {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Object(below)), Value::Object(above)) => merge(below, above),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
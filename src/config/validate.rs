@@ -0,0 +1,260 @@
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io;
+use std::path::Path;
+
+use super::GrokConfig;
+use crate::{error::ConfigError, throttle::RateLimit};
+
+/// One thing wrong with a [`GrokConfig`], found by [`GrokConfig::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigProblem {
+    pub field: String,
+    pub problem: String,
+    /// What to change to fix it.
+    pub fix: String,
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}. {}", self.field, self.problem, self.fix)
+    }
+}
+
+#[derive(Default)]
+struct Problems(Vec<ConfigProblem>);
+
+impl Problems {
+    fn add(&mut self, field: impl Into<String>, problem: impl Into<String>, fix: impl Into<String>) {
+        self.0.push(ConfigProblem {
+            field: field.into(),
+            problem: problem.into(),
+            fix: fix.into(),
+        });
+    }
+}
+
+impl GrokConfig {
+    /// Normalises harmless variations, such as `@GrokAI` or
+    /// `https://t.me/GrokAI` for `bot_username`, then checks every field.
+    ///
+    /// Fails with [`ConfigError::Validation`] listing all problems found.
+    /// [`GrokClient::new`](crate::GrokClient::new) calls this.
+    pub fn validate(&mut self) -> Result<(), ConfigError> {
+        self.bot_username = normalize_username(&self.bot_username);
+        self.api_hash = self.api_hash.trim().to_string();
+
+        let mut problems = Problems::default();
+
+        if self.api_id <= 0 {
+            problems.add(
+                "api_id",
+                format!("{} is not a valid api_id", self.api_id),
+                "Use the api_id shown at https://my.telegram.org/apps",
+            );
+        }
+        if self.api_hash.is_empty() {
+            problems.add(
+                "api_hash",
+                "is empty",
+                "Use the api_hash shown at https://my.telegram.org/apps",
+            );
+        } else if !self.api_hash.chars().all(|c| c.is_ascii_alphanumeric()) {
+            problems.add(
+                "api_hash",
+                "contains characters other than letters and digits",
+                "Replace the placeholder with the api_hash shown at https://my.telegram.org/apps",
+            );
+        }
+        check_username(&self.bot_username, &mut problems);
+        if let Some(id) = self.bot_id.filter(|id| *id <= 0) {
+            problems.add(
+                "bot_id",
+                format!("{id} is not a valid user id"),
+                "Use the bot's numeric id, or remove it to skip the check",
+            );
+        }
+        check_session_path(&self.session_path, &mut problems);
+
+        if self.response_timeout == 0 {
+            problems.add(
+                "response_timeout",
+                "is 0, so every request would time out",
+                "Set it to the number of seconds to wait for a reply, e.g. 30",
+            );
+        }
+        if self.stream_quiet_period_ms == 0 {
+            problems.add(
+                "stream_quiet_period_ms",
+                "is 0, so streams would end before the first edit",
+                "Set it to a few seconds, e.g. 3000",
+            );
+        }
+        if self.stream_final_marker.as_deref() == Some("") {
+            problems.add(
+                "stream_final_marker",
+                "is empty, so every reply would count as final",
+                "Remove it or set it to the text the bot ends replies with",
+            );
+        }
+        if self.reconnect_initial_delay_ms == 0 {
+            problems.add(
+                "reconnect_initial_delay_ms",
+                "is 0, so reconnect attempts would not back off",
+                "Set it to e.g. 500",
+            );
+        }
+        if self.reconnect_max_delay_ms < self.reconnect_initial_delay_ms {
+            problems.add(
+                "reconnect_max_delay_ms",
+                format!(
+                    "{} is less than reconnect_initial_delay_ms ({})",
+                    self.reconnect_max_delay_ms, self.reconnect_initial_delay_ms
+                ),
+                "Raise it or lower reconnect_initial_delay_ms",
+            );
+        }
+
+        check_rate_limit("rate_limit", &self.rate_limit, &mut problems);
+        let mut priorities: Vec<_> = self.priority_rate_limits.iter().collect();
+        priorities.sort_by_key(|(priority, _)| std::cmp::Reverse(**priority));
+        for (priority, limit) in priorities {
+            let field = format!("priority_rate_limits.{priority:?}");
+            check_rate_limit(&field, limit, &mut problems);
+        }
+
+        if problems.0.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Validation(problems.0))
+        }
+    }
+}
+
+/// Strips `@` and `t.me/` link prefixes from a username.
+fn normalize_username(username: &str) -> String {
+    let mut name = username.trim();
+    for scheme in ["https://", "http://"] {
+        name = name.strip_prefix(scheme).unwrap_or(name);
+    }
+    for host in ["www.", "t.me/", "telegram.me/", "telegram.dog/"] {
+        name = name.strip_prefix(host).unwrap_or(name);
+    }
+    name = name.strip_prefix('@').unwrap_or(name);
+    name.trim_end_matches('/').to_string()
+}
+
+fn check_username(username: &str, problems: &mut Problems) {
+    const FIX: &str = "Use the bot's username as shown in its profile, e.g. GrokAI";
+
+    if username.is_empty() {
+        problems.add("bot_username", "is empty", FIX);
+    } else if !username.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        problems.add(
+            "bot_username",
+            format!("{username:?} contains characters a username cannot have"),
+            FIX,
+        );
+    } else if !(5..=32).contains(&username.len()) {
+        problems.add(
+            "bot_username",
+            format!("{username:?} is not 5 to 32 characters long"),
+            FIX,
+        );
+    } else if !username.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        problems.add(
+            "bot_username",
+            format!("{username:?} does not start with a letter"),
+            FIX,
+        );
+    }
+}
+
+fn check_session_path(path: &Path, problems: &mut Problems) {
+    if path.as_os_str().is_empty() {
+        problems.add(
+            "session_path",
+            "is empty",
+            "Set it to a file to keep the session in, e.g. session.session",
+        );
+        return;
+    }
+    if path.is_dir() {
+        problems.add(
+            "session_path",
+            format!("{} is a directory", path.display()),
+            "Point it at a file inside that directory, e.g. session.session",
+        );
+        return;
+    }
+
+    if path.exists() {
+        if let Err(e) = OpenOptions::new().append(true).open(path) {
+            problems.add(
+                "session_path",
+                format!("{} is not writable: {e}", path.display()),
+                "Fix the file's permissions or choose another path",
+            );
+        }
+        return;
+    }
+
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    match parent.metadata() {
+        Ok(meta) if meta.is_dir() => {
+            if let Err(e) = check_writable(parent) {
+                problems.add(
+                    "session_path",
+                    format!("{} is not writable: {e}", parent.display()),
+                    "Fix the directory's permissions or choose another path",
+                );
+            }
+        }
+        _ => problems.add(
+            "session_path",
+            format!("directory {} does not exist", parent.display()),
+            "Create the directory or choose another path",
+        ),
+    }
+}
+
+/// Whether we may create files in `dir`. Permission bits alone do not
+/// tell, since they depend on who owns it.
+#[cfg(unix)]
+fn check_writable(dir: &Path) -> io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(dir.as_os_str().as_bytes())?;
+    if unsafe { libc::access(path.as_ptr(), libc::W_OK) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(unix))]
+fn check_writable(dir: &Path) -> io::Result<()> {
+    let probe = dir.join(format!(".grok-client-probe-{}", std::process::id()));
+    OpenOptions::new().write(true).create_new(true).open(&probe)?;
+    std::fs::remove_file(probe)
+}
+
+fn check_rate_limit(field: &str, limit: &RateLimit, problems: &mut Problems) {
+    if !(limit.per_second.is_finite() && limit.per_second > 0.0) {
+        problems.add(
+            format!("{field}.per_second"),
+            format!("{} is not a positive number", limit.per_second),
+            "Set it to how many messages may be sent per second, e.g. 1.0",
+        );
+    }
+    if limit.burst == 0 {
+        problems.add(
+            format!("{field}.burst"),
+            "is 0, so nothing could ever be sent",
+            "Set it to at least 1",
+        );
+    }
+}
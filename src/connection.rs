@@ -0,0 +1,97 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+use crate::{
+    error::GrokError,
+    events::{ClientEvent, Events},
+    transport::TelegramTransport,
+};
+
+/// Where the client stands with Telegram. Watch it through
+/// [`GrokClient::connection_state`](crate::GrokClient::connection_state).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    /// The connection dropped; retrying with backoff.
+    Reconnecting { attempt: u32 },
+    /// The session is no longer authorized and the client must sign in again.
+    AuthRequired,
+}
+
+/// Exponential backoff with "equal jitter": each delay is between half and
+/// all of the current step, and the step doubles up to `max`.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    step: Duration,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            step: initial,
+        }
+    }
+
+    pub fn next_delay(&mut self) -> Duration {
+        let step = self.step;
+        self.step = (self.step * 2).min(self.max);
+        let half = step / 2;
+        half + half.mul_f64(fastrand::f64())
+    }
+
+    pub fn reset(&mut self) {
+        self.step = self.initial;
+    }
+}
+
+/// Brings the transport back after the connection drops.
+pub(crate) struct Reconnector {
+    pub transport: Arc<dyn TelegramTransport>,
+    pub state: Arc<watch::Sender<ConnectionState>>,
+    pub events: Events,
+    pub backoff: Backoff,
+}
+
+impl Reconnector {
+    /// Reconnects until it succeeds, restoring the saved session. Returns
+    /// `false` if the session turned out to be no longer authorized.
+    pub async fn reconnect(&mut self) -> bool {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            self.set_state(ConnectionState::Reconnecting { attempt });
+            tokio::time::sleep(self.backoff.next_delay()).await;
+
+            match self.connect_authorized().await {
+                Ok(true) => {
+                    log::info!("Reconnected after {} attempt(s)", attempt);
+                    self.backoff.reset();
+                    self.set_state(ConnectionState::Connected);
+                    return true;
+                }
+                Ok(false) => {
+                    log::error!("Session is no longer authorized");
+                    self.set_state(ConnectionState::AuthRequired);
+                    return false;
+                }
+                Err(e) => log::warn!("Reconnect attempt {} failed: {}", attempt, e),
+            }
+        }
+    }
+
+    fn set_state(&self, state: ConnectionState) {
+        self.state.send_replace(state);
+        self.events.emit(ClientEvent::ConnectionStateChanged(state));
+    }
+
+    async fn connect_authorized(&self) -> Result<bool, GrokError> {
+        self.transport.connect().await?;
+        self.transport.is_authorized().await
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{
+    error::GrokError,
+    reply::{BotReply, RequestInfo},
+    request::{RequestStatus, Tracker},
+};
+
+pub(crate) type ReplyResult = Result<BotReply, GrokError>;
+
+// Replies that arrived before their request was registered (the update can
+// beat the `send_message` response) are kept here for a short while.
+const UNCLAIMED_LIMIT: usize = 32;
+// How many answered requests we remember to attribute later edits.
+const ANSWERED_LIMIT: usize = 64;
+
+/// Where the reply to a queued request should go.
+pub(crate) enum ReplySender {
+    /// Only the first reply message.
+    Once(oneshot::Sender<ReplyResult>),
+    /// The first reply message and every later edit of it.
+    Stream(mpsc::UnboundedSender<ReplyResult>),
+}
+
+impl ReplySender {
+    fn is_closed(&self) -> bool {
+        match self {
+            ReplySender::Once(tx) => tx.is_closed(),
+            ReplySender::Stream(tx) => tx.is_closed(),
+        }
+    }
+
+    /// Delivers the result, handing back the stream sender if the waiter
+    /// wants to follow edits.
+    pub fn deliver(self, result: ReplyResult) -> Option<mpsc::UnboundedSender<ReplyResult>> {
+        match self {
+            ReplySender::Once(tx) => {
+                let _ = tx.send(result);
+                None
+            }
+            ReplySender::Stream(tx) => tx.send(result).ok().map(|_| tx),
+        }
+    }
+}
+
+struct Pending {
+    request: RequestInfo,
+    reply: ReplySender,
+    status: Tracker,
+}
+
+#[derive(Default)]
+struct State {
+    pending: Vec<Pending>,
+    unclaimed: VecDeque<BotReply>,
+    // Bot message id -> request it answered.
+    answered: VecDeque<(i32, RequestInfo)>,
+    // Bot message id -> stream following its edits.
+    streams: HashMap<i32, mpsc::UnboundedSender<ReplyResult>>,
+}
+
+impl State {
+    fn deliver(&mut self, mut reply: BotReply, pending: Pending) {
+        let Pending {
+            request,
+            reply: sender,
+            status,
+        } = pending;
+        reply.request_message_id = Some(request.message_id);
+        let message_id = reply.message_id;
+        status.set(RequestStatus::Answered {
+            message_id: request.message_id,
+            reply_id: message_id,
+        });
+        if let Some(stream) = sender.deliver(Ok(reply)) {
+            self.streams.insert(message_id, stream);
+        }
+        if self.answered.len() == ANSWERED_LIMIT {
+            self.answered.pop_front();
+        }
+        self.answered.push_back((message_id, request));
+    }
+
+    fn answered(&self, message_id: i32) -> Option<&RequestInfo> {
+        self.answered
+            .iter()
+            .find(|(id, _)| *id == message_id)
+            .map(|(_, request)| request)
+    }
+}
+
+/// Matches bot replies to the outgoing messages that asked for them.
+///
+/// A reply belongs to the request it explicitly replies to; an unquoted
+/// reply belongs to the oldest waiting request sent before it in the bot's
+/// private chat.
+#[derive(Default)]
+pub(crate) struct Correlator {
+    state: Mutex<State>,
+}
+
+impl Correlator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&self, request: RequestInfo, reply: ReplySender, status: Tracker) {
+        let sent_id = request.message_id;
+        let pending = Pending {
+            request,
+            reply,
+            status,
+        };
+        let mut state = self.state.lock().unwrap();
+
+        let claimed = state.unclaimed.iter().position(|r| match r.reply_to {
+            Some(to) => to == sent_id,
+            None => r.message_id > sent_id,
+        });
+
+        match claimed.and_then(|i| state.unclaimed.remove(i)) {
+            Some(found) => state.deliver(found, pending),
+            None => {
+                let at = state.pending.partition_point(|p| p.request.message_id < sent_id);
+                state.pending.insert(at, pending);
+            }
+        }
+    }
+
+    /// Hands the reply to its waiting request, returning that request.
+    pub fn resolve(&self, reply: BotReply) -> Option<RequestInfo> {
+        let mut state = self.state.lock().unwrap();
+        state.pending.retain(|p| !p.reply.is_closed());
+
+        // A reply quoting a message nobody waits on belongs to no one
+        let index = match reply.reply_to {
+            Some(to) => state.pending.iter().position(|p| p.request.message_id == to),
+            None => {
+                let message_id = reply.message_id;
+                state.pending.iter().position(|p| p.request.message_id < message_id)
+            }
+        };
+
+        match index {
+            Some(i) => {
+                let pending = state.pending.remove(i);
+                let request = pending.request.clone();
+                state.deliver(reply, pending);
+                Some(request)
+            }
+            None => {
+                if state.unclaimed.len() == UNCLAIMED_LIMIT {
+                    state.unclaimed.pop_front();
+                }
+                state.unclaimed.push_back(reply);
+                None
+            }
+        }
+    }
+
+    /// Fails every waiting request and stops following streams.
+    pub fn fail_all(&self, error: impl Fn() -> GrokError) {
+        let mut state = self.state.lock().unwrap();
+        for pending in state.pending.drain(..) {
+            pending.status.set(RequestStatus::Failed(error().to_string()));
+            pending.reply.deliver(Err(error()));
+        }
+        for (_, stream) in state.streams.drain() {
+            let _ = stream.send(Err(error()));
+        }
+    }
+
+    /// Forwards an edited bot message to the stream following it, returning
+    /// the request the message answered.
+    pub fn resolve_edit(&self, mut reply: BotReply) -> Option<RequestInfo> {
+        let mut state = self.state.lock().unwrap();
+        state.streams.retain(|_, tx| !tx.is_closed());
+
+        if let Some(early) = state
+            .unclaimed
+            .iter_mut()
+            .find(|r| r.message_id == reply.message_id)
+        {
+            *early = reply;
+            return None;
+        }
+
+        let request = state.answered(reply.message_id)?.clone();
+        reply.request_message_id = Some(request.message_id);
+        if let Some(stream) = state.streams.get(&reply.message_id) {
+            let _ = stream.send(Ok(reply));
+        }
+        Some(request)
+    }
+}
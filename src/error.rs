@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+use std::time::Duration;
 use thiserror::Error;
 use grammers_client::{
     client::updates::AuthorizationError,
@@ -5,6 +7,8 @@ use grammers_client::{
     InvocationError
 };
 
+use crate::config::ConfigProblem;
+
 #[derive(Error, Debug)]
 pub enum GrokError {
     #[error("Authentication failed: {0}")]
@@ -16,6 +20,15 @@ pub enum GrokError {
     #[error("Session error: {0}")]
     Session(String),
 
+    /// Another client holds the lock on the session file.
+    #[error("Session {} is in use by process {pid} on {host}; if it is not running, delete {}", .session.display(), .lock.display())]
+    SessionLocked {
+        session: PathBuf,
+        lock: PathBuf,
+        pid: u32,
+        host: String,
+    },
+
     #[error("Authorization error: {0}")]
     Authorization(String),
 
@@ -27,6 +40,55 @@ pub enum GrokError {
 
     #[error("Bot error: {0}")]
     Bot(String),
+
+    /// `bot_username` resolved to another account than the pinned or
+    /// trusted bot id; nothing was sent to it.
+    #[error("@{username} is now account {actual}, not the expected bot {expected}")]
+    BotIdentityMismatch {
+        username: String,
+        expected: i64,
+        actual: i64,
+    },
+
+    #[error("No reply from the bot within {0:?}")]
+    Timeout(Duration),
+
+    /// Telegram asked us to wait this long before sending again.
+    #[error("Flood wait of {0:?}")]
+    FloodWait(Duration),
+
+    #[error("Client is shutting down")]
+    ShuttingDown,
+
+    #[error("Config error: {0}")]
+    Config(#[from] ConfigError),
+}
+
+/// Why a [`GrokConfig`](crate::GrokConfig) could not be loaded.
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("{key} is not set")]
+    Missing { key: &'static str },
+
+    #[error("{key}={value:?} is invalid: {reason}")]
+    Invalid {
+        key: String,
+        value: String,
+        reason: String,
+    },
+
+    #[error("Cannot read {}: {reason}", path.display())]
+    File { path: PathBuf, reason: String },
+
+    #[error("No config file defines profile {0:?}")]
+    UnknownProfile(String),
+
+    /// The merged layers do not form a valid config.
+    #[error("{0}")]
+    Malformed(String),
+
+    #[error("Invalid config:{}", .0.iter().map(|p| format!("\n  - {p}")).collect::<String>())]
+    Validation(Vec<ConfigProblem>),
 }
 
 impl From<SignInError> for GrokError {
@@ -43,6 +105,17 @@ impl From<AuthorizationError> for GrokError {
 
 impl From<InvocationError> for GrokError {
     fn from(e: InvocationError) -> Self {
-        GrokError::Invocation(e.to_string())
+        match e {
+            InvocationError::Rpc(ref rpc)
+                if matches!(rpc.name.as_str(), "FLOOD_WAIT" | "FLOOD_PREMIUM_WAIT") =>
+            {
+                GrokError::FloodWait(Duration::from_secs(rpc.value.unwrap_or(1).into()))
+            }
+            InvocationError::Rpc(_) => GrokError::Invocation(e.to_string()),
+            // The connection is gone rather than the request being wrong
+            InvocationError::Dropped | InvocationError::Read(_) => {
+                GrokError::Connection(e.to_string())
+            }
+        }
     }
 }
\ No newline at end of file
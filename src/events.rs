@@ -0,0 +1,71 @@
+//! Typed events for everything the client does, to follow from dashboards
+//! and tests instead of scraping logs.
+
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::{
+    client::GrokClient, connection::ConnectionState, queue::RequestPriority, reply::BotReply,
+    request::RequestId,
+};
+
+// Events a subscriber may fall behind by before it misses some.
+const CAPACITY: usize = 256;
+
+#[derive(Debug, Clone)]
+pub enum ClientEvent {
+    /// A message was queued for sending.
+    Queued {
+        id: RequestId,
+        priority: RequestPriority,
+        text: String,
+    },
+    Sent {
+        id: RequestId,
+        message_id: i32,
+        priority: RequestPriority,
+        text: String,
+    },
+    /// A message could not be sent and was dropped.
+    SendFailed {
+        id: RequestId,
+        priority: RequestPriority,
+        text: String,
+        error: String,
+    },
+    /// A new message from the bot, with `request_message_id` set if it was
+    /// matched to a prompt.
+    ReplyReceived(BotReply),
+    /// The bot edited one of its messages.
+    ReplyEdited(BotReply),
+    ConnectionStateChanged(ConnectionState),
+    /// Telegram throttled us; the queue pauses for `wait`.
+    FloodWait {
+        wait: Duration,
+    },
+}
+
+/// Where the client's tasks publish [`ClientEvent`]s.
+#[derive(Clone)]
+pub(crate) struct Events(broadcast::Sender<ClientEvent>);
+
+impl Events {
+    pub fn new() -> Self {
+        Self(broadcast::channel(CAPACITY).0)
+    }
+
+    pub fn emit(&self, event: ClientEvent) {
+        // Nobody listening is fine
+        let _ = self.0.send(event);
+    }
+}
+
+impl GrokClient {
+    /// Receives every event from now on.
+    ///
+    /// A receiver more than 256 events behind skips the oldest ones and gets
+    /// [`broadcast::error::RecvError::Lagged`].
+    pub fn subscribe(&self) -> broadcast::Receiver<ClientEvent> {
+        self.events.0.subscribe()
+    }
+}
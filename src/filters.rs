@@ -0,0 +1,120 @@
+//! Composable predicates for [`MessageHandler::with_filter`](crate::MessageHandler::with_filter).
+//!
+//! ```
+//! use grok_client::filters::{self, Filter};
+//!
+//! let filter = filters::from_bot()
+//!     .and(filters::regex(r"(?i)error").unwrap())
+//!     .and(filters::edited().not());
+//! ```
+
+use regex::Regex;
+
+use crate::{handlers::MessageContext, transport::MediaKind};
+
+pub trait Filter: Send + Sync + 'static {
+    fn matches(&self, ctx: &MessageContext) -> bool;
+
+    fn and<F: Filter>(self, other: F) -> And<Self, F>
+    where
+        Self: Sized,
+    {
+        And(self, other)
+    }
+
+    fn or<F: Filter>(self, other: F) -> Or<Self, F>
+    where
+        Self: Sized,
+    {
+        Or(self, other)
+    }
+
+    fn not(self) -> Not<Self>
+    where
+        Self: Sized,
+    {
+        Not(self)
+    }
+}
+
+impl<F> Filter for F
+where
+    F: Fn(&MessageContext) -> bool + Send + Sync + 'static,
+{
+    fn matches(&self, ctx: &MessageContext) -> bool {
+        self(ctx)
+    }
+}
+
+pub struct And<A, B>(A, B);
+
+impl<A: Filter, B: Filter> Filter for And<A, B> {
+    fn matches(&self, ctx: &MessageContext) -> bool {
+        self.0.matches(ctx) && self.1.matches(ctx)
+    }
+}
+
+pub struct Or<A, B>(A, B);
+
+impl<A: Filter, B: Filter> Filter for Or<A, B> {
+    fn matches(&self, ctx: &MessageContext) -> bool {
+        self.0.matches(ctx) || self.1.matches(ctx)
+    }
+}
+
+pub struct Not<A>(A);
+
+impl<A: Filter> Filter for Not<A> {
+    fn matches(&self, ctx: &MessageContext) -> bool {
+        !self.0.matches(ctx)
+    }
+}
+
+/// What a message consists of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    Text,
+    Media(MediaKind),
+}
+
+/// Messages in the chat with this id.
+pub fn chat(id: i64) -> impl Filter {
+    move |ctx: &MessageContext| ctx.chat().id == id
+}
+
+/// Messages from the configured bot in its private chat.
+pub fn from_bot() -> impl Filter {
+    |ctx: &MessageContext| ctx.is_from_bot()
+}
+
+/// Messages whose text matches `pattern`.
+pub fn regex(pattern: &str) -> Result<impl Filter, regex::Error> {
+    let regex = Regex::new(pattern)?;
+    Ok(move |ctx: &MessageContext| regex.is_match(ctx.text()))
+}
+
+/// Messages of the given kind.
+pub fn kind(kind: MessageKind) -> impl Filter {
+    move |ctx: &MessageContext| {
+        let actual = match ctx.media() {
+            Some(media) => MessageKind::Media(media),
+            None => MessageKind::Text,
+        };
+        actual == kind
+    }
+}
+
+/// Messages carrying any media.
+pub fn media() -> impl Filter {
+    |ctx: &MessageContext| ctx.media().is_some()
+}
+
+/// Edits of earlier messages.
+pub fn edited() -> impl Filter {
+    |ctx: &MessageContext| ctx.is_edit()
+}
+
+/// Messages matched to one of our prompts.
+pub fn answers() -> impl Filter {
+    |ctx: &MessageContext| ctx.request().is_some()
+}
@@ -0,0 +1,182 @@
+//! Rich text: converting Markdown and HTML to Telegram's formatting entities
+//! and back.
+//!
+//! Telegram sends text and its formatting separately. The text is plain, and
+//! each [`Entity`] marks a span of it as bold, code, a link and so on.
+
+use grammers_client::{grammers_tl_types as tl, parsers};
+
+/// A formatted span of a message.
+///
+/// `offset` and `length` count UTF-16 code units, as Telegram does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entity {
+    pub kind: EntityKind,
+    pub offset: usize,
+    pub length: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntityKind {
+    Bold,
+    Italic,
+    Underline,
+    Strikethrough,
+    Spoiler,
+    /// Inline code.
+    Code,
+    /// A code block, with the language it is written in if known.
+    Pre {
+        language: Option<String>,
+    },
+    /// Text linking to `url`.
+    TextUrl {
+        url: String,
+    },
+    /// A bare URL in the text.
+    Url,
+    Email,
+    Phone,
+    /// An `@username`.
+    Mention,
+    /// A user mentioned by id rather than by username.
+    MentionName {
+        user_id: i64,
+    },
+    Hashtag,
+    Cashtag,
+    BotCommand,
+    Blockquote,
+    /// Anything else, e.g. custom emoji.
+    Other,
+}
+
+impl Entity {
+    pub fn new(kind: EntityKind, offset: usize, length: usize) -> Self {
+        Self {
+            kind,
+            offset,
+            length,
+        }
+    }
+
+    /// The part of `text` this entity covers.
+    pub fn text(&self, text: &str) -> String {
+        let units: Vec<u16> = text
+            .encode_utf16()
+            .skip(self.offset)
+            .take(self.length)
+            .collect();
+        String::from_utf16_lossy(&units)
+    }
+}
+
+/// Splits Markdown into plain text and its entities.
+pub fn parse_markdown(markdown: &str) -> (String, Vec<Entity>) {
+    let (text, entities) = parsers::parse_markdown_message(markdown);
+    (text, entities.iter().map(from_tl).collect())
+}
+
+/// Splits HTML into plain text and its entities.
+pub fn parse_html(html: &str) -> (String, Vec<Entity>) {
+    let (text, entities) = parsers::parse_html_message(html);
+    (text, entities.iter().map(from_tl).collect())
+}
+
+/// Renders `text` with `entities` as Markdown. Entities Markdown cannot
+/// express, e.g. underline, are dropped.
+pub fn to_markdown(text: &str, entities: &[Entity]) -> String {
+    parsers::generate_markdown_message(text, &to_tl(entities))
+}
+
+/// Renders `text` with `entities` as HTML.
+pub fn to_html(text: &str, entities: &[Entity]) -> String {
+    parsers::generate_html_message(text, &to_tl(entities))
+}
+
+pub(crate) fn from_tl(entity: &tl::enums::MessageEntity) -> Entity {
+    use tl::enums::MessageEntity as ME;
+
+    let (kind, offset, length) = match entity {
+        ME::Bold(e) => (EntityKind::Bold, e.offset, e.length),
+        ME::Italic(e) => (EntityKind::Italic, e.offset, e.length),
+        ME::Underline(e) => (EntityKind::Underline, e.offset, e.length),
+        ME::Strike(e) => (EntityKind::Strikethrough, e.offset, e.length),
+        ME::Spoiler(e) => (EntityKind::Spoiler, e.offset, e.length),
+        ME::Code(e) => (EntityKind::Code, e.offset, e.length),
+        ME::Pre(e) => {
+            let language = Some(e.language.clone()).filter(|language| !language.is_empty());
+            (EntityKind::Pre { language }, e.offset, e.length)
+        }
+        ME::TextUrl(e) => {
+            let url = e.url.clone();
+            (EntityKind::TextUrl { url }, e.offset, e.length)
+        }
+        ME::Url(e) => (EntityKind::Url, e.offset, e.length),
+        ME::Email(e) => (EntityKind::Email, e.offset, e.length),
+        ME::Phone(e) => (EntityKind::Phone, e.offset, e.length),
+        ME::Mention(e) => (EntityKind::Mention, e.offset, e.length),
+        ME::MentionName(e) => {
+            let user_id = e.user_id;
+            (EntityKind::MentionName { user_id }, e.offset, e.length)
+        }
+        ME::Hashtag(e) => (EntityKind::Hashtag, e.offset, e.length),
+        ME::Cashtag(e) => (EntityKind::Cashtag, e.offset, e.length),
+        ME::BotCommand(e) => (EntityKind::BotCommand, e.offset, e.length),
+        ME::Blockquote(e) => (EntityKind::Blockquote, e.offset, e.length),
+        ME::Unknown(e) => (EntityKind::Other, e.offset, e.length),
+        ME::BankCard(e) => (EntityKind::Other, e.offset, e.length),
+        ME::CustomEmoji(e) => (EntityKind::Other, e.offset, e.length),
+        ME::InputMessageEntityMentionName(e) => (EntityKind::Other, e.offset, e.length),
+    };
+    Entity::new(kind, offset.max(0) as usize, length.max(0) as usize)
+}
+
+pub(crate) fn to_tl(entities: &[Entity]) -> Vec<tl::enums::MessageEntity> {
+    entities.iter().filter_map(entity_to_tl).collect()
+}
+
+fn entity_to_tl(entity: &Entity) -> Option<tl::enums::MessageEntity> {
+    use tl::enums::MessageEntity as ME;
+    use tl::types as t;
+
+    let offset = entity.offset as i32;
+    let length = entity.length as i32;
+    Some(match &entity.kind {
+        EntityKind::Bold => ME::Bold(t::MessageEntityBold { offset, length }),
+        EntityKind::Italic => ME::Italic(t::MessageEntityItalic { offset, length }),
+        EntityKind::Underline => ME::Underline(t::MessageEntityUnderline { offset, length }),
+        EntityKind::Strikethrough => ME::Strike(t::MessageEntityStrike { offset, length }),
+        EntityKind::Spoiler => ME::Spoiler(t::MessageEntitySpoiler { offset, length }),
+        EntityKind::Code => ME::Code(t::MessageEntityCode { offset, length }),
+        EntityKind::Pre { language } => ME::Pre(t::MessageEntityPre {
+            offset,
+            length,
+            language: language.clone().unwrap_or_default(),
+        }),
+        EntityKind::TextUrl { url } => ME::TextUrl(t::MessageEntityTextUrl {
+            offset,
+            length,
+            url: url.clone(),
+        }),
+        EntityKind::Url => ME::Url(t::MessageEntityUrl { offset, length }),
+        EntityKind::Email => ME::Email(t::MessageEntityEmail { offset, length }),
+        EntityKind::Phone => ME::Phone(t::MessageEntityPhone { offset, length }),
+        EntityKind::Mention => ME::Mention(t::MessageEntityMention { offset, length }),
+        EntityKind::MentionName { user_id } => ME::MentionName(t::MessageEntityMentionName {
+            offset,
+            length,
+            user_id: *user_id,
+        }),
+        EntityKind::Hashtag => ME::Hashtag(t::MessageEntityHashtag { offset, length }),
+        EntityKind::Cashtag => ME::Cashtag(t::MessageEntityCashtag { offset, length }),
+        EntityKind::BotCommand => ME::BotCommand(t::MessageEntityBotCommand { offset, length }),
+        EntityKind::Blockquote => ME::Blockquote(t::MessageEntityBlockquote {
+            collapsed: false,
+            offset,
+            length,
+        }),
+        // Telegram would reject these without the data we dropped
+        EntityKind::Other => return None,
+    })
+}
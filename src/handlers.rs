@@ -1,11 +1,272 @@
-use crate::client::GrokClient;
+use async_trait::async_trait;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock, Weak};
+use tokio::sync::Mutex;
 
+use crate::{
+    client::GrokClient,
+    error::GrokError,
+    events::{ClientEvent, Events},
+    filters::Filter,
+    media::Attachment,
+    queue::{PriorityQueue, RequestPriority},
+    reply::RequestInfo,
+    transport::{IncomingMessage, MediaKind, OutgoingMessage, Peer},
+};
+
+/// Whether later handlers should see the message too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Propagation {
+    Continue,
+    Stop,
+}
+
+/// A message delivered to handlers, with what we know about it.
+#[derive(Clone)]
+pub struct MessageContext {
+    message: IncomingMessage,
+    edited: bool,
+    from_bot: bool,
+    request: Option<RequestInfo>,
+    queue: Arc<Mutex<PriorityQueue>>,
+    accepting: Arc<AtomicBool>,
+    events: Events,
+}
+
+impl MessageContext {
+    pub(crate) fn new(
+        message: IncomingMessage,
+        edited: bool,
+        from_bot: bool,
+        request: Option<RequestInfo>,
+        queue: Arc<Mutex<PriorityQueue>>,
+        accepting: Arc<AtomicBool>,
+        events: Events,
+    ) -> Self {
+        Self {
+            message,
+            edited,
+            from_bot,
+            request,
+            queue,
+            accepting,
+            events,
+        }
+    }
+
+    pub fn message_id(&self) -> i32 {
+        self.message.id
+    }
+
+    pub fn chat(&self) -> Peer {
+        self.message.chat
+    }
+
+    pub fn sender_id(&self) -> Option<i64> {
+        self.message.sender_id
+    }
+
+    pub fn text(&self) -> &str {
+        &self.message.text
+    }
+
+    pub fn is_edit(&self) -> bool {
+        self.edited
+    }
+
+    /// Whether the message comes from the configured bot in its private chat.
+    pub fn is_from_bot(&self) -> bool {
+        self.from_bot
+    }
+
+    pub fn media(&self) -> Option<MediaKind> {
+        self.message.media.as_ref().map(|media| media.kind)
+    }
+
+    /// The attached file, to download with [`GrokClient::download`].
+    pub fn attachment(&self) -> Option<&Attachment> {
+        self.message.media.as_ref()
+    }
+
+    pub fn reply_to(&self) -> Option<i32> {
+        self.message.reply_to
+    }
+
+    /// Our prompt this message answers, if it was matched to one.
+    pub fn request(&self) -> Option<&RequestInfo> {
+        self.request.as_ref()
+    }
+
+    pub fn message(&self) -> &IncomingMessage {
+        &self.message
+    }
+
+    /// Queues `text` as a reply to this message, in the same chat. Fails
+    /// with [`GrokError::ShuttingDown`] once the client is shutting down.
+    pub async fn reply(&self, text: &str) -> Result<(), GrokError> {
+        let message = OutgoingMessage {
+            reply_to: Some(self.message.id),
+            ..OutgoingMessage::text(text)
+        };
+        self.respond_with(message).await
+    }
+
+    /// Queues `text` in the same chat without quoting this message.
+    pub async fn respond(&self, text: &str) -> Result<(), GrokError> {
+        self.respond_with(OutgoingMessage::text(text)).await
+    }
+
+    async fn respond_with(&self, message: OutgoingMessage) -> Result<(), GrokError> {
+        if !self.accepting.load(Ordering::SeqCst) {
+            return Err(GrokError::ShuttingDown);
+        }
+        let text = message.text.clone();
+        let priority = RequestPriority::Normal;
+        let id = self.queue.lock().await.push_to(self.message.chat, message, priority);
+        self.events.emit(ClientEvent::Queued { id, priority, text });
+        Ok(())
+    }
+}
+
+/// Receives incoming messages and their edits.
+///
+/// Handlers run one after another on a dedicated task, so a slow handler
+/// delays the ones after it but never the listener.
+#[async_trait]
 pub trait MessageHandler: Send + Sync {
-    fn handle(&self, message: &str);
+    async fn handle(&self, ctx: &MessageContext) -> Propagation;
+
+    /// Only runs this handler for messages matching `filter`.
+    fn with_filter<F: Filter>(self, filter: F) -> Filtered<Self, F>
+    where
+        Self: Sized,
+    {
+        Filtered {
+            handler: self,
+            filter,
+        }
+    }
+}
+
+/// See [`MessageHandler::with_filter`].
+pub struct Filtered<H, F> {
+    handler: H,
+    filter: F,
+}
+
+#[async_trait]
+impl<H: MessageHandler, F: Filter> MessageHandler for Filtered<H, F> {
+    async fn handle(&self, ctx: &MessageContext) -> Propagation {
+        if self.filter.matches(ctx) {
+            self.handler.handle(ctx).await
+        } else {
+            Propagation::Continue
+        }
+    }
+}
+
+/// Adapts an async closure into a [`MessageHandler`].
+pub fn handler_fn<F, Fut>(f: F) -> FnHandler<F>
+where
+    F: Fn(MessageContext) -> Fut + Send + Sync,
+    Fut: Future<Output = Propagation> + Send,
+{
+    FnHandler(f)
+}
+
+/// See [`handler_fn`].
+pub struct FnHandler<F>(F);
+
+#[async_trait]
+impl<F, Fut> MessageHandler for FnHandler<F>
+where
+    F: Fn(MessageContext) -> Fut + Send + Sync,
+    Fut: Future<Output = Propagation> + Send,
+{
+    async fn handle(&self, ctx: &MessageContext) -> Propagation {
+        (self.0)(ctx.clone()).await
+    }
+}
+
+struct Entry {
+    id: u64,
+    order: i32,
+    handler: Arc<dyn MessageHandler>,
+}
+
+#[derive(Default)]
+pub(crate) struct HandlerRegistry {
+    entries: RwLock<Vec<Entry>>,
+    next_id: AtomicU64,
+}
+
+impl HandlerRegistry {
+    fn add(self: &Arc<Self>, order: i32, handler: Arc<dyn MessageHandler>) -> HandlerHandle {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut entries = self.entries.write().unwrap();
+        // Keep registration order among handlers with the same `order`
+        let at = entries.partition_point(|e| e.order <= order);
+        entries.insert(at, Entry { id, order, handler });
+        HandlerHandle {
+            id,
+            registry: Arc::downgrade(self),
+        }
+    }
+
+    fn remove(&self, id: u64) -> bool {
+        let mut entries = self.entries.write().unwrap();
+        let before = entries.len();
+        entries.retain(|e| e.id != id);
+        entries.len() != before
+    }
+
+    pub async fn dispatch(&self, ctx: &MessageContext) {
+        let handlers: Vec<_> = self
+            .entries
+            .read()
+            .unwrap()
+            .iter()
+            .map(|e| e.handler.clone())
+            .collect();
+
+        for handler in handlers {
+            if handler.handle(ctx).await == Propagation::Stop {
+                break;
+            }
+        }
+    }
+}
+
+/// Returned when registering a handler. Dropping it keeps the handler
+/// registered; call [`HandlerHandle::remove`] to unregister.
+pub struct HandlerHandle {
+    id: u64,
+    registry: Weak<HandlerRegistry>,
+}
+
+impl HandlerHandle {
+    /// Unregisters the handler. Returns `false` if it was already removed.
+    pub fn remove(self) -> bool {
+        self.registry
+            .upgrade()
+            .is_some_and(|registry| registry.remove(self.id))
+    }
 }
 
 impl GrokClient {
-    pub fn add_custom_handler<H: MessageHandler>(&self, handler: H) {
-        // Регистрация кастомного обработчика
+    /// Registers a handler that runs after those already registered.
+    pub fn add_custom_handler<H: MessageHandler + 'static>(&self, handler: H) -> HandlerHandle {
+        self.add_handler_with_order(0, handler)
+    }
+
+    /// Registers a handler at position `order`: lower values run first,
+    /// equal values run in registration order.
+    pub fn add_handler_with_order<H: MessageHandler + 'static>(
+        &self,
+        order: i32,
+        handler: H,
+    ) -> HandlerHandle {
+        self.handlers.add(order, Arc::new(handler))
     }
 }
\ No newline at end of file
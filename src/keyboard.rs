@@ -0,0 +1,134 @@
+//! Inline buttons under bot messages, and pressing them.
+
+use tokio::sync::mpsc;
+
+use crate::{
+    client::GrokClient,
+    error::GrokError,
+    handlers::{handler_fn, Propagation},
+    reply::BotReply,
+};
+
+/// A button under a bot message, laid out in rows as in
+/// [`BotReply::buttons`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Button {
+    pub text: String,
+    pub kind: ButtonKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ButtonKind {
+    /// Sends `data` back to the bot when pressed.
+    Callback(Vec<u8>),
+    /// Opens a URL without telling the bot.
+    Url(String),
+    /// Anything else, e.g. switching to inline mode or sharing a contact.
+    Other,
+}
+
+impl Button {
+    pub fn callback(text: impl Into<String>, data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            text: text.into(),
+            kind: ButtonKind::Callback(data.into()),
+        }
+    }
+
+    pub fn url(text: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            kind: ButtonKind::Url(url.into()),
+        }
+    }
+
+    /// What pressing the button sends to the bot, if anything.
+    pub fn data(&self) -> Option<&[u8]> {
+        match &self.kind {
+            ButtonKind::Callback(data) => Some(data),
+            _ => None,
+        }
+    }
+}
+
+/// How the bot acknowledged a button press.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallbackAnswer {
+    /// A notification to show.
+    pub message: Option<String>,
+    /// Whether `message` should be shown as an alert rather than a toast.
+    pub alert: bool,
+    /// A URL the bot wants opened.
+    pub url: Option<String>,
+}
+
+/// What [`GrokClient::press`] got back.
+#[derive(Debug, Clone)]
+pub enum ButtonResponse {
+    /// The bot answered with a notification or a URL.
+    Answer(CallbackAnswer),
+    /// The bot edited the message the button was under, or sent a new one.
+    Reply(BotReply),
+}
+
+impl GrokClient {
+    /// Presses `button` under `reply` and waits for the bot to react.
+    ///
+    /// Returns the bot's notification if it shows one, otherwise the
+    /// message it edits or sends next. Fails with [`GrokError::Timeout`] if
+    /// it does neither within `GrokConfig::response_timeout` seconds.
+    /// Requires [`GrokClient::start`].
+    pub async fn press(
+        &self,
+        reply: &BotReply,
+        button: &Button,
+    ) -> Result<ButtonResponse, GrokError> {
+        let Some(data) = button.data() else {
+            return Err(GrokError::Bot(format!(
+                "Button {:?} does not send anything to the bot",
+                button.text
+            )));
+        };
+
+        // Watch before pressing: the bot may react before it answers
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let message_id = reply.message_id;
+        let watcher = self.add_handler_with_order(
+            i32::MIN,
+            handler_fn(move |ctx| {
+                let tx = tx.clone();
+                async move {
+                    let follows = if ctx.is_edit() {
+                        ctx.message_id() == message_id
+                    } else {
+                        ctx.message_id() > message_id
+                    };
+                    if ctx.is_from_bot() && follows {
+                        let _ = tx.send(BotReply::from_incoming(
+                            ctx.message().clone(),
+                            ctx.is_edit(),
+                        ));
+                    }
+                    Propagation::Continue
+                }
+            }),
+        );
+
+        let result = match self
+            .transport
+            .press_button(self.bot, message_id, data)
+            .await
+        {
+            Ok(answer) if answer.message.is_some() || answer.url.is_some() => {
+                Ok(ButtonResponse::Answer(answer))
+            }
+            Ok(_) => match tokio::time::timeout(self.response_timeout, rx.recv()).await {
+                Ok(Some(reply)) => Ok(ButtonResponse::Reply(reply)),
+                _ => Err(GrokError::Timeout(self.response_timeout)),
+            },
+            Err(e) => Err(e),
+        };
+        watcher.remove();
+        result
+    }
+}
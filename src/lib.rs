@@ -1,18 +1,62 @@
+pub mod account;
+pub mod auth;
 pub mod config;
 pub mod client;
+pub mod connection;
 pub mod error;
+pub mod events;
+pub mod filters;
+pub mod format;
+pub mod handlers;
+pub mod keyboard;
+pub mod lifecycle;
+pub mod media;
 pub mod queue;
+pub mod reply;
+pub mod request;
+pub mod session;
+pub mod stream;
+pub mod testing;
+pub mod throttle;
+pub mod transport;
 
-pub use config::GrokConfig;
-pub use client::GrokClient;
-pub use error::GrokError;
+mod correlation;
+
+pub use config::{ConfigLoader, ConfigProblem, GrokConfig};
+pub use auth::{
+    AuthPrompter, ChannelPrompter, CodeDelivery, EnvPrompter, LoginMethod, QrLogin,
+    TerminalPrompter,
+};
+pub use client::{GrokClient, GrokClientBuilder};
+pub use connection::{Backoff, ConnectionState};
+pub use error::{ConfigError, GrokError};
+pub use events::ClientEvent;
+pub use format::{Entity, EntityKind};
+pub use media::{Attachment, Upload};
+pub use keyboard::{Button, ButtonKind, ButtonResponse, CallbackAnswer};
+pub use lifecycle::{ClientHandle, ShutdownReport};
+pub use handlers::{handler_fn, HandlerHandle, MessageContext, MessageHandler, Propagation};
 pub use queue::RequestPriority;
+pub use reply::{BotReply, RequestInfo};
+pub use request::{RequestHandle, RequestId, RequestStatus};
+pub use session::{
+    EncryptedSessionStore, FileSessionStore, MemorySessionStore, SessionKey, SessionLock,
+    SessionStore, StringSessionStore,
+};
+pub use stream::{ReplyChunk, ReplyStream};
+pub use throttle::{FloodWaitStats, RateLimit};
+pub use transport::{GrammersTransport, MemoryTransport, OutgoingMessage, TelegramTransport};
 
 pub mod prelude {
     pub use crate::{
         GrokConfig,
         GrokClient,
         GrokError,
-        RequestPriority
+        RequestPriority,
+        BotReply,
+        MessageHandler,
+        MessageContext,
+        Propagation,
+        handler_fn
     };
 }
\ No newline at end of file
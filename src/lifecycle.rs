@@ -0,0 +1,142 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::{
+    correlation::Correlator,
+    error::GrokError,
+    queue::{PriorityQueue, RequestPriority},
+    request::RequestStatus,
+    session::SessionLock,
+    transport::{OutgoingMessage, TelegramTransport},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Phase {
+    Running,
+    /// Send what is queued until the deadline, accept nothing new.
+    Draining { deadline: Instant },
+    Stopped,
+}
+
+/// What was left over after [`ClientHandle::shutdown`].
+#[derive(Debug, Default)]
+pub struct ShutdownReport {
+    /// Messages still queued when the deadline passed, highest priority
+    /// first. Persist or re-send them on the next start.
+    pub unsent: Vec<(OutgoingMessage, RequestPriority)>,
+    /// Names of background tasks that panicked.
+    pub panicked: Vec<&'static str>,
+    /// Names of background tasks still busy after the deadline, e.g. in a
+    /// slow handler, which were aborted.
+    pub aborted: Vec<&'static str>,
+    /// Why the session could not be saved, if it could not.
+    pub session_error: Option<String>,
+}
+
+impl ShutdownReport {
+    pub fn is_clean(&self) -> bool {
+        self.unsent.is_empty()
+            && self.panicked.is_empty()
+            && self.aborted.is_empty()
+            && self.session_error.is_none()
+    }
+}
+
+// How long tasks still running at the shutdown deadline get to finish.
+const GRACE: Duration = Duration::from_millis(500);
+
+/// Controls the background tasks spawned by [`GrokClient::start`](crate::GrokClient::start).
+///
+/// Dropping the handle leaves the tasks running.
+pub struct ClientHandle {
+    pub(crate) phase: watch::Sender<Phase>,
+    pub(crate) accepting: Arc<AtomicBool>,
+    pub(crate) queue: Arc<Mutex<PriorityQueue>>,
+    pub(crate) correlator: Arc<Correlator>,
+    pub(crate) transport: Arc<dyn TelegramTransport>,
+    pub(crate) tasks: Vec<(&'static str, JoinHandle<()>)>,
+    /// Released once the session has been saved for the last time.
+    pub(crate) session_lock: Option<Arc<SessionLock>>,
+}
+
+impl ClientHandle {
+    /// Stops accepting new messages, keeps sending queued ones for up to
+    /// `deadline`, then stops every task and waits for it to finish. Tasks
+    /// still running shortly after the deadline are aborted.
+    ///
+    /// Requests still waiting for a reply fail with
+    /// [`GrokError::ShuttingDown`]. The session is saved last.
+    pub async fn shutdown(self, deadline: Duration) -> ShutdownReport {
+        self.accepting.store(false, Ordering::SeqCst);
+        let deadline = Instant::now() + deadline;
+        let _ = self.phase.send(Phase::Draining { deadline });
+
+        let mut report = ShutdownReport::default();
+        let mut tasks = self.tasks.into_iter();
+
+        // The sender goes first so the listener can still match replies
+        // while the queue drains. It may be stuck in a send past the deadline.
+        for (name, task) in tasks.by_ref() {
+            let is_sender = name == "sender";
+            join(name, task, deadline + GRACE, &mut report).await;
+            if is_sender {
+                break;
+            }
+        }
+
+        let _ = self.phase.send(Phase::Stopped);
+        let until = Instant::now().max(deadline) + GRACE;
+        for (name, task) in tasks {
+            join(name, task, until, &mut report).await;
+        }
+
+        for item in self.queue.lock().await.drain_items() {
+            let failed = RequestStatus::Failed(GrokError::ShuttingDown.to_string());
+            if !item.status.advance(|s| *s == RequestStatus::Queued, failed) {
+                // Cancelled, but not taken out of the queue yet
+                continue;
+            }
+            if let Some(reply) = item.reply {
+                reply.deliver(Err(GrokError::ShuttingDown));
+            }
+            report.unsent.push((item.message, item.priority));
+        }
+        self.correlator.fail_all(|| GrokError::ShuttingDown);
+
+        if let Err(e) = self.transport.save_session() {
+            log::error!("Failed to save session: {}", e);
+            report.session_error = Some(e.to_string());
+        }
+        // The client may still hold it too
+        drop(self.session_lock);
+
+        report
+    }
+
+    /// Whether any background task has stopped, normally or by panicking.
+    pub fn is_finished(&self) -> bool {
+        self.tasks.iter().any(|(_, task)| task.is_finished())
+    }
+}
+
+/// Waits for `task` until `until`, then aborts it.
+async fn join(
+    name: &'static str,
+    mut task: JoinHandle<()>,
+    until: Instant,
+    report: &mut ShutdownReport,
+) {
+    match tokio::time::timeout_at(until, &mut task).await {
+        Ok(Err(e)) if e.is_panic() => report.panicked.push(name),
+        Ok(_) => {}
+        Err(_) => {
+            log::warn!("Task {} did not stop in time, aborting it", name);
+            task.abort();
+            report.aborted.push(name);
+        }
+    }
+}
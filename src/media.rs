@@ -0,0 +1,195 @@
+//! Files sent to the bot and files it sends back.
+
+use std::any::Any;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{client::GrokClient, error::GrokError, transport::MediaKind};
+
+#[derive(Clone)]
+enum Source {
+    Path(PathBuf),
+    Bytes(Arc<[u8]>),
+}
+
+/// A photo, document or voice note to send, read from a file or memory.
+///
+/// Send it with a caption through
+/// [`OutgoingMessage::with_media`](crate::transport::OutgoingMessage::with_media).
+#[derive(Clone)]
+pub struct Upload {
+    kind: MediaKind,
+    source: Source,
+    name: String,
+    mime_type: Option<String>,
+    duration: Option<Duration>,
+}
+
+impl Upload {
+    pub fn photo(path: impl Into<PathBuf>) -> Self {
+        Self::from_path(MediaKind::Photo, path.into())
+    }
+
+    pub fn photo_bytes(bytes: impl Into<Vec<u8>>, name: impl Into<String>) -> Self {
+        Self::from_bytes(MediaKind::Photo, bytes.into(), name.into())
+    }
+
+    pub fn document(path: impl Into<PathBuf>) -> Self {
+        Self::from_path(MediaKind::Document, path.into())
+    }
+
+    pub fn document_bytes(bytes: impl Into<Vec<u8>>, name: impl Into<String>) -> Self {
+        Self::from_bytes(MediaKind::Document, bytes.into(), name.into())
+    }
+
+    /// A voice note, which Telegram expects as OGG/Opus.
+    pub fn voice(path: impl Into<PathBuf>) -> Self {
+        Self::from_path(MediaKind::Voice, path.into()).with_mime_type("audio/ogg")
+    }
+
+    pub fn voice_bytes(bytes: impl Into<Vec<u8>>, name: impl Into<String>) -> Self {
+        Self::from_bytes(MediaKind::Voice, bytes.into(), name.into()).with_mime_type("audio/ogg")
+    }
+
+    fn from_path(kind: MediaKind, path: PathBuf) -> Self {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self {
+            kind,
+            source: Source::Path(path),
+            name,
+            mime_type: None,
+            duration: None,
+        }
+    }
+
+    fn from_bytes(kind: MediaKind, bytes: Vec<u8>, name: String) -> Self {
+        Self {
+            kind,
+            source: Source::Bytes(bytes.into()),
+            name,
+            mime_type: None,
+            duration: None,
+        }
+    }
+
+    /// Overrides the MIME type guessed from the file name.
+    pub fn with_mime_type(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_type = Some(mime_type.into());
+        self
+    }
+
+    /// How long a voice note plays, shown before it is downloaded.
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    pub fn kind(&self) -> MediaKind {
+        self.kind
+    }
+
+    /// The file name the bot sees.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn mime_type(&self) -> Option<&str> {
+        self.mime_type.as_deref()
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    /// The file it was created from, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match &self.source {
+            Source::Path(path) => Some(path),
+            Source::Bytes(_) => None,
+        }
+    }
+
+    /// The contents, reading the file if it came from one.
+    pub async fn read(&self) -> Result<Vec<u8>, GrokError> {
+        match &self.source {
+            Source::Path(path) => Ok(tokio::fs::read(path).await?),
+            Source::Bytes(bytes) => Ok(bytes.to_vec()),
+        }
+    }
+}
+
+impl fmt::Debug for Upload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("Upload");
+        debug.field("kind", &self.kind).field("name", &self.name);
+        match &self.source {
+            Source::Path(path) => debug.field("path", path),
+            Source::Bytes(bytes) => debug.field("bytes", &bytes.len()),
+        };
+        debug.field("mime_type", &self.mime_type).finish()
+    }
+}
+
+/// A file attached to a message, such as an image the bot generated.
+///
+/// Fetch its contents with [`GrokClient::download`].
+#[derive(Clone)]
+pub struct Attachment {
+    pub kind: MediaKind,
+    pub mime_type: Option<String>,
+    /// In bytes; 0 if Telegram did not say.
+    pub size: u64,
+    pub file_name: Option<String>,
+    /// What the transport needs to download it.
+    handle: Arc<dyn Any + Send + Sync>,
+}
+
+impl Attachment {
+    pub fn new<T: Any + Send + Sync>(kind: MediaKind, handle: T) -> Self {
+        Self {
+            kind,
+            mime_type: None,
+            size: 0,
+            file_name: None,
+            handle: Arc::new(handle),
+        }
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.handle.downcast_ref()
+    }
+}
+
+impl fmt::Debug for Attachment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Attachment")
+            .field("kind", &self.kind)
+            .field("mime_type", &self.mime_type)
+            .field("size", &self.size)
+            .field("file_name", &self.file_name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GrokClient {
+    /// The contents of `attachment`.
+    pub async fn download(&self, attachment: &Attachment) -> Result<Vec<u8>, GrokError> {
+        self.transport.download(attachment).await
+    }
+
+    /// Saves `attachment` to `path`.
+    pub async fn download_to(
+        &self,
+        attachment: &Attachment,
+        path: impl AsRef<Path>,
+    ) -> Result<(), GrokError> {
+        let data = self.download(attachment).await?;
+        tokio::fs::write(path, data).await?;
+        Ok(())
+    }
+}
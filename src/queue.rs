@@ -1,7 +1,15 @@
-use grammers_client::InputMessage;
+use serde::Deserialize;
 use std::collections::BinaryHeap;
+use std::sync::Arc;
+use tokio::sync::Notify;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+use crate::{
+    correlation::ReplySender,
+    request::{RequestId, Tracker},
+    transport::{OutgoingMessage, Peer},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
 pub enum RequestPriority {
     Emergency = 5,
     High = 3,
@@ -10,9 +18,14 @@ pub enum RequestPriority {
 }
 
 // Убрать #[derive(Debug)]
-struct QueueItem {
-    message: InputMessage,
-    priority: RequestPriority,
+pub(crate) struct QueueItem {
+    pub id: RequestId,
+    pub message: OutgoingMessage,
+    pub priority: RequestPriority,
+    pub reply: Option<ReplySender>,
+    /// Recipient, if not the bot.
+    pub target: Option<Peer>,
+    pub status: Tracker,
 }
 
 impl PartialOrd for QueueItem {
@@ -23,13 +36,16 @@ impl PartialOrd for QueueItem {
 
 impl Ord for QueueItem {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.priority.cmp(&other.priority).reverse()
+        // Higher priority first, then first in, first out
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.id.cmp(&self.id))
     }
 }
 
 impl PartialEq for QueueItem {
     fn eq(&self, other: &Self) -> bool {
-        self.priority == other.priority
+        self.cmp(other).is_eq()
     }
 }
 
@@ -37,20 +53,145 @@ impl Eq for QueueItem {}
 
 pub struct PriorityQueue {
     inner: BinaryHeap<QueueItem>,
+    next_seq: u64,
+    pushed: Arc<Notify>,
+}
+
+impl Default for PriorityQueue {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PriorityQueue {
     pub fn new() -> Self {
         Self {
             inner: BinaryHeap::new(),
+            next_seq: 0,
+            pushed: Arc::new(Notify::new()),
         }
     }
 
-    pub fn push(&mut self, message: InputMessage, priority: RequestPriority) {
-        self.inner.push(QueueItem { message, priority });
+    pub fn push(&mut self, message: OutgoingMessage, priority: RequestPriority) {
+        self.push_item(message, priority, None, None);
     }
 
-    pub fn pop(&mut self) -> Option<(InputMessage, RequestPriority)> {
+    pub fn pop(&mut self) -> Option<(OutgoingMessage, RequestPriority)> {
         self.inner.pop().map(|item| (item.message, item.priority))
     }
+
+    /// Like [`PriorityQueue::push`], returning what is needed to follow
+    /// the message.
+    pub(crate) fn push_tracked(
+        &mut self,
+        message: OutgoingMessage,
+        priority: RequestPriority,
+    ) -> (RequestId, Tracker) {
+        self.push_item(message, priority, None, None)
+    }
+
+    pub(crate) fn push_request(
+        &mut self,
+        message: OutgoingMessage,
+        priority: RequestPriority,
+        reply: ReplySender,
+    ) -> (RequestId, Tracker) {
+        self.push_item(message, priority, Some(reply), None)
+    }
+
+    pub(crate) fn push_to(
+        &mut self,
+        target: Peer,
+        message: OutgoingMessage,
+        priority: RequestPriority,
+    ) -> RequestId {
+        self.push_item(message, priority, None, Some(target)).0
+    }
+
+    /// Takes request `id` out of the queue, if it is still there.
+    pub(crate) fn remove(&mut self, id: RequestId) -> Option<QueueItem> {
+        let (mut removed, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.inner)
+            .into_vec()
+            .into_iter()
+            .partition(|item| item.id == id);
+        self.inner = kept.into();
+        removed.pop()
+    }
+
+    /// Pops the first item, in queue order, whose priority passes `ready`.
+    pub(crate) fn pop_item_where(
+        &mut self,
+        mut ready: impl FnMut(RequestPriority) -> bool,
+    ) -> Option<QueueItem> {
+        let mut skipped = Vec::new();
+        let mut blocked = Vec::new();
+        let mut found = None;
+        while let Some(item) = self.inner.pop() {
+            if !blocked.contains(&item.priority) {
+                if ready(item.priority) {
+                    found = Some(item);
+                    break;
+                }
+                blocked.push(item.priority);
+            }
+            skipped.push(item);
+        }
+        self.inner.extend(skipped);
+        found
+    }
+
+    /// Priorities that have something queued.
+    pub(crate) fn priorities(&self) -> Vec<RequestPriority> {
+        let mut priorities: Vec<_> = self.inner.iter().map(|item| item.priority).collect();
+        priorities.sort_unstable();
+        priorities.dedup();
+        priorities
+    }
+
+    /// Woken whenever something is pushed.
+    pub(crate) fn notifier(&self) -> Arc<Notify> {
+        self.pushed.clone()
+    }
+
+    /// Puts back an item taken with `pop_item_where`, ahead of anything
+    /// queued after it at the same priority.
+    pub(crate) fn requeue(&mut self, item: QueueItem) {
+        self.inner.push(item);
+        self.pushed.notify_one();
+    }
+
+    /// Removes every item, highest priority first.
+    pub(crate) fn drain_items(&mut self) -> Vec<QueueItem> {
+        std::iter::from_fn(|| self.inner.pop()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    fn push_item(
+        &mut self,
+        message: OutgoingMessage,
+        priority: RequestPriority,
+        reply: Option<ReplySender>,
+        target: Option<Peer>,
+    ) -> (RequestId, Tracker) {
+        let id = RequestId(self.next_seq);
+        self.next_seq += 1;
+        let status = Tracker::new();
+        self.inner.push(QueueItem {
+            id,
+            message,
+            priority,
+            reply,
+            target,
+            status: status.clone(),
+        });
+        self.pushed.notify_one();
+        (id, status)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,68 @@
+use crate::{
+    format::{self, Entity},
+    keyboard::Button,
+    media::Attachment,
+    transport::IncomingMessage,
+};
+
+/// A message the bot sent in its private chat with us.
+#[derive(Debug, Clone)]
+pub struct BotReply {
+    pub message_id: i32,
+    pub chat_id: i64,
+    pub sender_id: i64,
+    /// The message, or the caption of `media`.
+    pub text: String,
+    /// Formatting of `text`: bold, code blocks, links and so on.
+    pub entities: Vec<Entity>,
+    /// A file the bot sent, e.g. a generated image.
+    pub media: Option<Attachment>,
+    /// Inline buttons under the message, row by row. Press them with
+    /// [`GrokClient::press`](crate::GrokClient::press).
+    pub buttons: Vec<Vec<Button>>,
+    pub reply_to: Option<i32>,
+    /// Whether this is an edit of a message the bot sent earlier.
+    pub edited: bool,
+    /// Id of our outgoing message this reply was matched to, if any.
+    pub request_message_id: Option<i32>,
+}
+
+impl BotReply {
+    pub(crate) fn from_incoming(message: IncomingMessage, edited: bool) -> Self {
+        Self {
+            message_id: message.id,
+            chat_id: message.chat.id,
+            sender_id: message.sender_id.unwrap_or_default(),
+            text: message.text,
+            entities: message.entities,
+            media: message.media,
+            buttons: message.buttons,
+            reply_to: message.reply_to,
+            edited,
+            request_message_id: None,
+        }
+    }
+
+    /// The first button labelled `text`.
+    pub fn button(&self, text: &str) -> Option<&Button> {
+        self.buttons.iter().flatten().find(|button| button.text == text)
+    }
+
+    /// The text with its formatting as Markdown, keeping code fences and
+    /// links.
+    pub fn to_markdown(&self) -> String {
+        format::to_markdown(&self.text, &self.entities)
+    }
+
+    /// The text with its formatting as HTML.
+    pub fn to_html(&self) -> String {
+        format::to_html(&self.text, &self.entities)
+    }
+}
+
+/// The prompt a bot message was matched to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestInfo {
+    pub message_id: i32,
+    pub text: String,
+}
@@ -0,0 +1,213 @@
+//! Following a queued message until it is sent or answered.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{oneshot, watch, Mutex};
+
+use crate::{correlation::ReplyResult, error::GrokError, queue::PriorityQueue, reply::BotReply};
+
+/// Identifies a queued message for the life of the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RequestId(pub(crate) u64);
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Where a queued message stands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestStatus {
+    Queued,
+    Sending,
+    Sent {
+        message_id: i32,
+    },
+    /// The bot replied with message `reply_id`.
+    Answered {
+        message_id: i32,
+        reply_id: i32,
+    },
+    Failed(String),
+    /// No reply within `GrokConfig::response_timeout` seconds.
+    Expired,
+    /// Removed from the queue before it was sent.
+    Cancelled,
+}
+
+/// The sending side of a request's status, shared by the queue, the sender
+/// and the correlator.
+#[derive(Clone)]
+pub(crate) struct Tracker(Arc<watch::Sender<RequestStatus>>);
+
+impl Tracker {
+    pub fn new() -> Self {
+        Self(Arc::new(watch::Sender::new(RequestStatus::Queued)))
+    }
+
+    pub fn set(&self, status: RequestStatus) {
+        self.0.send_replace(status);
+    }
+
+    /// Moves to `to` if the status passes `from`. Returns whether it did.
+    pub fn advance(&self, from: impl FnOnce(&RequestStatus) -> bool, to: RequestStatus) -> bool {
+        self.0.send_if_modified(|status| {
+            let advance = from(status);
+            if advance {
+                *status = to;
+            }
+            advance
+        })
+    }
+
+    /// Claims a queued request for sending. Fails if it was cancelled.
+    pub fn start_sending(&self) -> bool {
+        self.advance(|s| *s == RequestStatus::Queued, RequestStatus::Sending)
+    }
+
+    fn cancel(&self) -> bool {
+        self.advance(|s| *s == RequestStatus::Queued, RequestStatus::Cancelled)
+    }
+
+    /// Expires a request still waiting for its reply.
+    pub fn expire(&self) {
+        self.advance(
+            |s| matches!(s, RequestStatus::Sent { .. }),
+            RequestStatus::Expired,
+        );
+    }
+}
+
+/// Returned by [`GrokClient::send`](crate::GrokClient::send) and
+/// [`GrokClient::request`](crate::GrokClient::request) to follow the message.
+///
+/// Dropping the handle leaves the message queued.
+pub struct RequestHandle {
+    id: RequestId,
+    status: watch::Receiver<RequestStatus>,
+    tracker: Tracker,
+    queue: Arc<Mutex<PriorityQueue>>,
+    reply: Option<oneshot::Receiver<ReplyResult>>,
+    response_timeout: Duration,
+    cancel_on_drop: bool,
+}
+
+impl RequestHandle {
+    pub(crate) fn new(
+        id: RequestId,
+        tracker: Tracker,
+        queue: Arc<Mutex<PriorityQueue>>,
+        reply: Option<oneshot::Receiver<ReplyResult>>,
+        response_timeout: Duration,
+    ) -> Self {
+        Self {
+            id,
+            status: tracker.0.subscribe(),
+            tracker,
+            queue,
+            reply,
+            response_timeout,
+            cancel_on_drop: false,
+        }
+    }
+
+    /// Cancels the request if it is still queued when the handle is dropped.
+    pub(crate) fn cancel_on_drop(mut self) -> Self {
+        self.cancel_on_drop = true;
+        self
+    }
+
+    pub fn id(&self) -> RequestId {
+        self.id
+    }
+
+    pub fn status(&self) -> RequestStatus {
+        self.status.borrow().clone()
+    }
+
+    /// Waits until the message has been sent, or answered if it expects a
+    /// reply, or has failed or been cancelled. A request still unanswered
+    /// `GrokConfig::response_timeout` seconds after it was sent expires.
+    pub async fn wait(&mut self) -> RequestStatus {
+        self.dequeued().await;
+        let sent = matches!(*self.status.borrow(), RequestStatus::Sent { .. });
+        if sent && self.reply.is_some() {
+            let answered = self
+                .status
+                .wait_for(|s| !matches!(s, RequestStatus::Sent { .. }));
+            if tokio::time::timeout(self.response_timeout, answered)
+                .await
+                .is_err()
+            {
+                self.tracker.expire();
+            }
+        }
+        self.status()
+    }
+
+    /// Waits until the message has left the queue: sent, failed or
+    /// cancelled.
+    pub(crate) async fn dequeued(&mut self) {
+        // The tracker lives in `self`, so the channel never closes
+        let _ = self
+            .status
+            .wait_for(|s| !matches!(s, RequestStatus::Queued | RequestStatus::Sending))
+            .await;
+    }
+
+    /// Expires the request if it is still waiting for its reply.
+    pub(crate) fn expire(&self) {
+        self.tracker.expire();
+    }
+
+    /// Removes the message from the queue. Returns `false` if it is no
+    /// longer queued, e.g. because it is being sent.
+    pub async fn cancel(&self) -> bool {
+        if !self.tracker.cancel() {
+            return false;
+        }
+        self.queue.lock().await.remove(self.id);
+        true
+    }
+
+    /// Waits for the bot's reply.
+    ///
+    /// Fails with [`GrokError::Timeout`] if none arrives within
+    /// `GrokConfig::response_timeout` seconds of the message being sent, and
+    /// with [`GrokError::Bot`] for a message queued with
+    /// [`GrokClient::send`](crate::GrokClient::send).
+    pub async fn reply(mut self) -> Result<BotReply, GrokError> {
+        let Some(rx) = self.reply.take() else {
+            return Err(GrokError::Bot(
+                "This request does not expect a reply".into(),
+            ));
+        };
+        // Time spent queued, e.g. behind a flood wait, does not count. A
+        // failed or cancelled send closes `rx`, so there is no need to check.
+        self.dequeued().await;
+        match tokio::time::timeout(self.response_timeout, rx).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(GrokError::Bot(
+                "Request was dropped before the bot replied".into(),
+            )),
+            Err(_) => {
+                self.tracker.expire();
+                Err(GrokError::Timeout(self.response_timeout))
+            }
+        }
+    }
+}
+
+impl Drop for RequestHandle {
+    fn drop(&mut self) {
+        if self.cancel_on_drop && self.tracker.cancel() {
+            // The sender skips cancelled items anyway; this just frees the
+            // slot early when the queue is not busy
+            if let Ok(mut queue) = self.queue.try_lock() {
+                queue.remove(self.id);
+            }
+        }
+    }
+}
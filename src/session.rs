@@ -0,0 +1,258 @@
+//! Where the Telegram session (the account's login) is kept.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::error::GrokError;
+
+mod encrypted;
+mod lock;
+
+pub use encrypted::{encrypt_session_file, EncryptedSessionStore, SessionKey};
+pub use lock::SessionLock;
+
+/// Loads and saves the serialized session.
+pub trait SessionStore: Send + Sync + 'static {
+    /// The saved session, or `None` if nothing has been saved yet.
+    fn load(&self) -> Result<Option<Vec<u8>>, GrokError>;
+
+    fn save(&self, data: &[u8]) -> Result<(), GrokError>;
+
+    /// The file the session is kept in, if any. A client locks it with
+    /// [`SessionLock`] while it runs.
+    fn file_path(&self) -> Option<&Path> {
+        None
+    }
+
+    /// Forgets the saved session, e.g. after logging out. Saves an empty
+    /// session by default.
+    fn clear(&self) -> Result<(), GrokError> {
+        self.save(&[])
+    }
+
+    /// The id recorded for bot `username` by [`SessionStore::trust_bot`].
+    /// Records nothing by default.
+    fn trusted_bot(&self, _username: &str) -> Result<Option<i64>, GrokError> {
+        Ok(None)
+    }
+
+    /// Records `id` as the bot `username` resolves to, for
+    /// `GrokConfig::trust_bot_on_first_use`.
+    fn trust_bot(&self, _username: &str, _id: i64) -> Result<(), GrokError> {
+        Err(GrokError::Session(
+            "This session store cannot record trusted bots".into(),
+        ))
+    }
+}
+
+/// Keeps the session in a file, `GrokConfig::session_path` by default.
+///
+/// New files are created readable only by their owner.
+#[derive(Debug, Clone)]
+pub struct FileSessionStore {
+    path: PathBuf,
+    private: bool,
+}
+
+impl FileSessionStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            private: false,
+        }
+    }
+
+    /// Refuses to load the file if users other than its owner can access it.
+    pub fn private(mut self) -> Self {
+        self.private = true;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Trusted bot ids are kept as JSON in `<session>.bots`.
+    fn bots(&self) -> Self {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".bots");
+        Self {
+            path: path.into(),
+            private: self.private,
+        }
+    }
+
+    fn load_bots(&self) -> Result<HashMap<String, i64>, GrokError> {
+        let store = self.bots();
+        match store.load()? {
+            Some(data) => serde_json::from_slice(&data).map_err(|e| {
+                GrokError::Session(format!("{} is corrupt: {}", store.path.display(), e))
+            }),
+            None => Ok(HashMap::new()),
+        }
+    }
+}
+
+impl SessionStore for FileSessionStore {
+    fn load(&self) -> Result<Option<Vec<u8>>, GrokError> {
+        if self.private && self.path.exists() {
+            check_private(&self.path)?;
+        }
+        match fs::read(&self.path) {
+            // Older versions created an empty file before the first save
+            Ok(data) if data.is_empty() => Ok(None),
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn file_path(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+
+    fn save(&self, data: &[u8]) -> Result<(), GrokError> {
+        // Write a sibling file and rename it over, so a crash mid-save never
+        // leaves a truncated session behind
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&tmp)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), GrokError> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn trusted_bot(&self, username: &str) -> Result<Option<i64>, GrokError> {
+        Ok(self.load_bots()?.get(&username.to_lowercase()).copied())
+    }
+
+    fn trust_bot(&self, username: &str, id: i64) -> Result<(), GrokError> {
+        let mut bots = self.load_bots()?;
+        bots.insert(username.to_lowercase(), id);
+        let json = serde_json::to_vec_pretty(&bots).expect("a map of ids serializes");
+        self.bots().save(&json)
+    }
+}
+
+/// Keeps the session in memory only. Clones share the same session.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySessionStore {
+    data: Arc<Mutex<Option<Vec<u8>>>>,
+    bots: Arc<Mutex<HashMap<String, i64>>>,
+}
+
+impl MemorySessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self) -> Option<Vec<u8>> {
+        self.data.lock().unwrap().clone()
+    }
+}
+
+impl SessionStore for MemorySessionStore {
+    fn load(&self) -> Result<Option<Vec<u8>>, GrokError> {
+        Ok(self.get())
+    }
+
+    fn save(&self, data: &[u8]) -> Result<(), GrokError> {
+        *self.data.lock().unwrap() = Some(data.to_vec());
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), GrokError> {
+        *self.data.lock().unwrap() = None;
+        Ok(())
+    }
+
+    fn trusted_bot(&self, username: &str) -> Result<Option<i64>, GrokError> {
+        Ok(self.bots.lock().unwrap().get(&username.to_lowercase()).copied())
+    }
+
+    fn trust_bot(&self, username: &str, id: i64) -> Result<(), GrokError> {
+        self.bots.lock().unwrap().insert(username.to_lowercase(), id);
+        Ok(())
+    }
+}
+
+/// Keeps the session as a base64 string, e.g. to pass it in an
+/// environment variable or a secrets manager. Clones share the same session.
+#[derive(Debug, Clone, Default)]
+pub struct StringSessionStore {
+    encoded: Arc<Mutex<String>>,
+}
+
+impl StringSessionStore {
+    /// Starts from a string saved earlier with [`StringSessionStore::get`];
+    /// an empty string means no session.
+    pub fn new(encoded: impl Into<String>) -> Self {
+        Self {
+            encoded: Arc::new(Mutex::new(encoded.into())),
+        }
+    }
+
+    /// The session as base64, empty if nothing has been saved.
+    pub fn get(&self) -> String {
+        self.encoded.lock().unwrap().clone()
+    }
+}
+
+impl SessionStore for StringSessionStore {
+    fn load(&self) -> Result<Option<Vec<u8>>, GrokError> {
+        let encoded = self.get();
+        let encoded = encoded.trim();
+        if encoded.is_empty() {
+            return Ok(None);
+        }
+        STANDARD
+            .decode(encoded)
+            .map(Some)
+            .map_err(|e| GrokError::Session(format!("Session string is not valid base64: {}", e)))
+    }
+
+    fn save(&self, data: &[u8]) -> Result<(), GrokError> {
+        *self.encoded.lock().unwrap() = STANDARD.encode(data);
+        Ok(())
+    }
+}
+
+/// Fails if users other than the owner can access `path`.
+#[cfg(unix)]
+fn check_private(path: &Path) -> Result<(), GrokError> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(path)?.permissions().mode() & 0o777;
+    if mode & 0o077 != 0 {
+        return Err(GrokError::Session(format!(
+            "{} is accessible by other users (mode {:o}); run `chmod 600 {}`",
+            path.display(),
+            mode,
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_private(_path: &Path) -> Result<(), GrokError> {
+    Ok(())
+}
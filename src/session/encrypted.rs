@@ -0,0 +1,226 @@
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use zeroize::Zeroizing;
+
+use super::{FileSessionStore, SessionStore};
+use crate::error::GrokError;
+
+/// Marks an encrypted session and its format version.
+const MAGIC: &[u8; 8] = b"GROKSES\x01";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = MAGIC.len() + SALT_LEN + NONCE_LEN;
+
+type Key = Zeroizing<[u8; 32]>;
+
+/// The secret an [`EncryptedSessionStore`] derives its key from.
+pub struct SessionKey {
+    secret: Zeroizing<Vec<u8>>,
+}
+
+impl SessionKey {
+    pub fn from_passphrase(passphrase: &str) -> Self {
+        Self {
+            secret: Zeroizing::new(passphrase.as_bytes().to_vec()),
+        }
+    }
+
+    /// Reads a key file, which must only be accessible to its owner.
+    pub fn from_keyfile(path: impl AsRef<Path>) -> Result<Self, GrokError> {
+        let path = path.as_ref();
+        super::check_private(path)?;
+        let secret = Zeroizing::new(fs::read(path)?);
+        if secret.len() < 16 {
+            return Err(GrokError::Session(format!(
+                "Key file {} is too short; create one with `grok-session keygen`",
+                path.display()
+            )));
+        }
+        Ok(Self { secret })
+    }
+
+    /// Writes a new random key file, readable only by its owner.
+    pub fn generate_keyfile(path: impl AsRef<Path>) -> Result<Self, GrokError> {
+        let path = path.as_ref();
+        let mut secret = Zeroizing::new(vec![0u8; 32]);
+        random(&mut secret)?;
+        if path.exists() {
+            return Err(GrokError::Session(format!(
+                "{} already exists; refusing to overwrite a key file",
+                path.display()
+            )));
+        }
+        FileSessionStore::new(path).save(&secret)?;
+        Ok(Self { secret })
+    }
+
+    fn derive(&self, salt: &[u8]) -> Result<Key, GrokError> {
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::default()
+            .hash_password_into(&self.secret, salt, key.as_mut())
+            .map_err(|e| GrokError::Session(format!("Key derivation failed: {}", e)))?;
+        Ok(key)
+    }
+}
+
+/// Encrypts the session before handing it to another store, with
+/// XChaCha20-Poly1305 and a key derived from a [`SessionKey`] by Argon2id.
+///
+/// A session that was modified, or is loaded with the wrong key, fails to
+/// load rather than being used.
+pub struct EncryptedSessionStore {
+    inner: Box<dyn SessionStore>,
+    key: SessionKey,
+    /// The salt in use and the key derived from it, so saving does not
+    /// rerun the key derivation every time.
+    derived: Mutex<Option<([u8; SALT_LEN], Key)>>,
+}
+
+impl EncryptedSessionStore {
+    pub fn new(inner: impl SessionStore, key: SessionKey) -> Self {
+        Self {
+            inner: Box::new(inner),
+            key,
+            derived: Mutex::new(None),
+        }
+    }
+
+    /// An encrypted session file that refuses to load when other users can
+    /// access it.
+    pub fn file(path: impl Into<PathBuf>, key: SessionKey) -> Self {
+        Self::new(FileSessionStore::new(path).private(), key)
+    }
+
+    fn key_for(&self, salt: &[u8; SALT_LEN]) -> Result<Key, GrokError> {
+        let mut derived = self.derived.lock().unwrap();
+        if let Some((cached_salt, key)) = derived.as_ref() {
+            if cached_salt == salt {
+                return Ok(key.clone());
+            }
+        }
+        let key = self.key.derive(salt)?;
+        *derived = Some((*salt, key.clone()));
+        Ok(key)
+    }
+}
+
+impl SessionStore for EncryptedSessionStore {
+    fn load(&self) -> Result<Option<Vec<u8>>, GrokError> {
+        let Some(data) = self.inner.load()? else {
+            return Ok(None);
+        };
+        if !is_encrypted(&data) {
+            return Err(GrokError::Session(
+                "Session is not encrypted; convert it with `grok-session encrypt`".into(),
+            ));
+        }
+        if data.len() < HEADER_LEN {
+            return Err(GrokError::Session("Encrypted session is truncated".into()));
+        }
+
+        let (header, ciphertext) = data.split_at(HEADER_LEN);
+        let salt: [u8; SALT_LEN] = header[MAGIC.len()..MAGIC.len() + SALT_LEN]
+            .try_into()
+            .unwrap();
+        let nonce = XNonce::from_slice(&header[MAGIC.len() + SALT_LEN..]);
+        let key = self.key_for(&salt)?;
+
+        XChaCha20Poly1305::new(key.as_ref().into())
+            .decrypt(
+                nonce,
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map(Some)
+            .map_err(|_| {
+                GrokError::Session(
+                    "Session could not be decrypted: the key is wrong or the file was modified"
+                        .into(),
+                )
+            })
+    }
+
+    fn save(&self, data: &[u8]) -> Result<(), GrokError> {
+        let salt = match self.derived.lock().unwrap().as_ref() {
+            Some((salt, _)) => *salt,
+            None => {
+                let mut salt = [0u8; SALT_LEN];
+                random(&mut salt)?;
+                salt
+            }
+        };
+        let key = self.key_for(&salt)?;
+        let mut nonce = [0u8; NONCE_LEN];
+        random(&mut nonce)?;
+
+        let mut out = Vec::with_capacity(HEADER_LEN + data.len() + 16);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&salt);
+        out.extend_from_slice(&nonce);
+        let ciphertext = XChaCha20Poly1305::new(key.as_ref().into())
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: data,
+                    aad: &out,
+                },
+            )
+            .map_err(|_| GrokError::Session("Session encryption failed".into()))?;
+        out.extend_from_slice(&ciphertext);
+
+        self.inner.save(&out)
+    }
+
+    fn clear(&self) -> Result<(), GrokError> {
+        self.inner.clear()
+    }
+
+    fn file_path(&self) -> Option<&Path> {
+        self.inner.file_path()
+    }
+
+    // Bot ids are public, so they are kept unencrypted
+    fn trusted_bot(&self, username: &str) -> Result<Option<i64>, GrokError> {
+        self.inner.trusted_bot(username)
+    }
+
+    fn trust_bot(&self, username: &str, id: i64) -> Result<(), GrokError> {
+        self.inner.trust_bot(username, id)
+    }
+}
+
+fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+fn random(buf: &mut [u8]) -> Result<(), GrokError> {
+    getrandom::getrandom(buf).map_err(|e| GrokError::Session(format!("No randomness: {}", e)))
+}
+
+/// Encrypts the plain session file at `plain` into `out`, which may be the
+/// same path. Fails if `plain` is already encrypted or not a session.
+pub fn encrypt_session_file(
+    plain: impl AsRef<Path>,
+    out: impl Into<PathBuf>,
+    key: SessionKey,
+) -> Result<(), GrokError> {
+    let plain = plain.as_ref();
+    let data = Zeroizing::new(fs::read(plain)?);
+    if is_encrypted(&data) {
+        return Err(GrokError::Session(format!(
+            "{} is already encrypted",
+            plain.display()
+        )));
+    }
+    grammers_session::Session::load(&data).map_err(|e| {
+        GrokError::Session(format!("{} is not a session file: {}", plain.display(), e))
+    })?;
+
+    EncryptedSessionStore::file(out, key).save(&data)
+}
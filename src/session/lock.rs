@@ -0,0 +1,138 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::GrokError;
+
+/// Keeps other processes from using a session file while held.
+///
+/// The lock is a `<session>.lock` file next to the session, locked by the
+/// OS for as long as it is held and holding the owner's PID and hostname.
+/// The OS lock goes away with the process, so a lock file left behind by a
+/// crash is taken over; one written on another host is not, since the OS
+/// lock may not reach across a network share. The file is removed on drop.
+#[derive(Debug)]
+pub struct SessionLock {
+    path: PathBuf,
+    // Holds the OS lock until dropped
+    _file: File,
+}
+
+impl SessionLock {
+    /// Fails with [`GrokError::SessionLocked`] if another process holds it.
+    pub fn acquire(session: impl AsRef<Path>) -> Result<Self, GrokError> {
+        let session = session.as_ref();
+        let mut name = session.as_os_str().to_owned();
+        name.push(".lock");
+        let path = PathBuf::from(name);
+        let host = hostname();
+        let locked = |pid, host| GrokError::SessionLocked {
+            session: session.to_path_buf(),
+            lock: path.clone(),
+            pid,
+            host,
+        };
+
+        loop {
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)?;
+            match file.try_lock() {
+                Ok(()) => {}
+                Err(TryLockError::WouldBlock) => {
+                    let (pid, owner) = read_owner(&path)?.unwrap_or_default();
+                    return Err(locked(pid, owner));
+                }
+                Err(TryLockError::Error(e)) => return Err(e.into()),
+            }
+            // The previous holder removed the file between our open and
+            // lock; whoever creates the next one gets it
+            if !is_same_file(&file, &path)? {
+                continue;
+            }
+
+            match read_owner(&path)? {
+                Some((pid, owner)) if pid != 0 && owner != host => {
+                    return Err(locked(pid, owner));
+                }
+                Some((pid, _)) if pid != 0 => {
+                    log::warn!(
+                        "Taking over stale lock {} left by process {}",
+                        path.display(),
+                        pid
+                    );
+                }
+                _ => {}
+            }
+            file.set_len(0)?;
+            writeln!(file, "{}\n{}", std::process::id(), host)?;
+            return Ok(Self { path, _file: file });
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for SessionLock {
+    fn drop(&mut self) {
+        // Removed while still locked, see `is_same_file`
+        if let Err(e) = fs::remove_file(&self.path) {
+            log::warn!("Could not remove lock {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Whether `path` still names the file we opened.
+#[cfg(unix)]
+fn is_same_file(file: &File, path: &Path) -> io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+
+    let opened = file.metadata()?;
+    match fs::metadata(path) {
+        Ok(current) => Ok(opened.dev() == current.dev() && opened.ino() == current.ino()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Open files cannot be removed here, so the path cannot have moved on.
+#[cfg(not(unix))]
+fn is_same_file(_file: &File, _path: &Path) -> io::Result<bool> {
+    Ok(true)
+}
+
+/// The PID and hostname in a lock file, with PID 0 if it cannot be parsed.
+fn read_owner(path: &Path) -> Result<Option<(u32, String)>, GrokError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut lines = text.lines();
+    let pid = lines.next().and_then(|pid| pid.trim().parse().ok());
+    let host = lines.next().map(str::trim).unwrap_or_default();
+    Ok(Some(match pid {
+        Some(pid) if !host.is_empty() => (pid, host.to_string()),
+        _ => (0, host.to_string()),
+    }))
+}
+
+#[cfg(unix)]
+fn hostname() -> String {
+    let mut buf = [0u8; 256];
+    if unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } != 0 {
+        return "unknown".into();
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+#[cfg(not(unix))]
+fn hostname() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_else(|_| "unknown".into())
+}
@@ -0,0 +1,144 @@
+use futures::Stream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::{
+    correlation::ReplyResult, error::GrokError, reply::BotReply, request::RequestHandle,
+};
+
+/// A snapshot of a reply the bot is still writing.
+#[derive(Debug, Clone)]
+pub struct ReplyChunk {
+    pub message_id: i32,
+    /// Full text of the reply so far.
+    pub text: String,
+    /// Text appended since the previous chunk. If the bot rewrote earlier
+    /// text instead of appending, this is the whole new text.
+    pub delta: String,
+    /// Set on the last chunk of the stream.
+    pub is_final: bool,
+}
+
+/// Reply to [`GrokClient::ask_stream`](crate::GrokClient::ask_stream), built
+/// from the bot editing its first message.
+pub struct ReplyStream {
+    rx: mpsc::UnboundedReceiver<Result<ReplyChunk, GrokError>>,
+}
+
+impl ReplyStream {
+    pub(crate) fn spawn(
+        request: RequestHandle,
+        edits: mpsc::UnboundedReceiver<ReplyResult>,
+        first_timeout: Duration,
+        quiet_period: Duration,
+        final_marker: Option<String>,
+    ) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(follow_edits(
+            request,
+            edits,
+            tx,
+            first_timeout,
+            quiet_period,
+            final_marker,
+        ));
+        Self { rx }
+    }
+
+    /// Waits for the reply to complete and returns its final text.
+    pub async fn final_text(mut self) -> Result<String, GrokError> {
+        let mut text = String::new();
+        while let Some(chunk) = self.rx.recv().await {
+            text = chunk?.text;
+        }
+        Ok(text)
+    }
+}
+
+impl Stream for ReplyStream {
+    type Item = Result<ReplyChunk, GrokError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+async fn follow_edits(
+    mut request: RequestHandle,
+    mut edits: mpsc::UnboundedReceiver<ReplyResult>,
+    tx: mpsc::UnboundedSender<Result<ReplyChunk, GrokError>>,
+    first_timeout: Duration,
+    quiet_period: Duration,
+    final_marker: Option<String>,
+) {
+    // Dropping `request` while the prompt is still queued cancels it
+    tokio::select! {
+        _ = request.dequeued() => {}
+        _ = tx.closed() => return,
+    }
+    let first = match tokio::time::timeout(first_timeout, edits.recv()).await {
+        Ok(Some(Ok(reply))) => reply,
+        Ok(Some(Err(e))) => {
+            let _ = tx.send(Err(e));
+            return;
+        }
+        Ok(None) => {
+            let _ = tx.send(Err(GrokError::Bot(
+                "Request was dropped before the bot replied".into(),
+            )));
+            return;
+        }
+        Err(_) => {
+            request.expire();
+            let _ = tx.send(Err(GrokError::Timeout(first_timeout)));
+            return;
+        }
+    };
+
+    let is_final = |reply: &BotReply| {
+        final_marker
+            .as_deref()
+            .is_some_and(|marker| reply.text.trim_end().ends_with(marker))
+    };
+
+    let mut last = String::new();
+    let mut current = first;
+    loop {
+        let done = is_final(&current);
+        let delta = match current.text.strip_prefix(last.as_str()) {
+            Some(appended) => appended.to_string(),
+            None => current.text.clone(),
+        };
+        let chunk = ReplyChunk {
+            message_id: current.message_id,
+            text: current.text.clone(),
+            delta,
+            is_final: done,
+        };
+        if tx.send(Ok(chunk)).is_err() || done {
+            return;
+        }
+        last = current.text;
+
+        current = match tokio::time::timeout(quiet_period, edits.recv()).await {
+            Ok(Some(Ok(reply))) => reply,
+            // E.g. shutting down: the text so far is not the whole reply
+            Ok(Some(Err(e))) => {
+                let _ = tx.send(Err(e));
+                return;
+            }
+            // The bot went quiet: the last snapshot is the final text.
+            Ok(None) | Err(_) => {
+                let _ = tx.send(Ok(ReplyChunk {
+                    message_id: current.message_id,
+                    text: last,
+                    delta: String::new(),
+                    is_final: true,
+                }));
+                return;
+            }
+        };
+    }
+}
@@ -0,0 +1,317 @@
+//! A scriptable bot on top of [`MemoryTransport`] for offline tests.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), grok_client::GrokError> {
+//! use grok_client::prelude::*;
+//! use grok_client::testing::{FakeBot, Rule};
+//! use std::time::Duration;
+//!
+//! let bot = FakeBot::new("GrokAI", 42);
+//! bot.add_rule(Rule::matching("^ping").wait(Duration::from_millis(200)).reply("pong"));
+//!
+//! let client = GrokClient::with_transport(bot.config(), bot.transport()).await?;
+//! client.start();
+//! assert_eq!(client.ask("ping", RequestPriority::Normal).await?.text, "pong");
+//! # Ok(())
+//! # }
+//! ```
+
+use regex::Regex;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use crate::{
+    config::GrokConfig,
+    format::{self, Entity},
+    keyboard::{Button, CallbackAnswer},
+    media::Upload,
+    transport::{ButtonPress, IncomingMessage, MemoryTransport, PeerKind, SentMessage},
+};
+
+#[derive(Debug, Clone)]
+enum Action {
+    Reply(String),
+    ReplyFormatted(String, Vec<Entity>),
+    ReplyMedia(String, Upload),
+    ReplyButtons(String, Vec<Vec<Button>>),
+    Answer(String),
+    Edit(String),
+    Wait(Duration),
+}
+
+/// What the bot does when a prompt matches `pattern`, or when one of its
+/// buttons is pressed.
+///
+/// Actions run in the order they were added. A rule without actions keeps
+/// the bot silent.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pattern: Regex,
+    pressed: Option<Vec<u8>>,
+    actions: Vec<Action>,
+    quote: bool,
+}
+
+impl Rule {
+    /// Panics if `pattern` is not a valid regex.
+    pub fn matching(pattern: &str) -> Self {
+        Self {
+            pattern: Regex::new(pattern).expect("invalid FakeBot rule pattern"),
+            pressed: None,
+            actions: Vec::new(),
+            quote: true,
+        }
+    }
+
+    /// Matches every prompt.
+    pub fn any() -> Self {
+        Self::matching("")
+    }
+
+    /// Matches presses of buttons carrying `data` instead of prompts.
+    /// [`Rule::edit`] then edits the message the button is under.
+    pub fn pressed(data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            pressed: Some(data.into()),
+            ..Self::any()
+        }
+    }
+
+    /// Sends a new message.
+    pub fn reply(mut self, text: impl Into<String>) -> Self {
+        self.actions.push(Action::Reply(text.into()));
+        self
+    }
+
+    /// Sends a new message written in Markdown, with Telegram formatting.
+    pub fn reply_markdown(mut self, markdown: &str) -> Self {
+        let (text, entities) = format::parse_markdown(markdown);
+        self.actions.push(Action::ReplyFormatted(text, entities));
+        self
+    }
+
+    /// Sends a new message written in HTML, with Telegram formatting.
+    pub fn reply_html(mut self, html: &str) -> Self {
+        let (text, entities) = format::parse_html(html);
+        self.actions.push(Action::ReplyFormatted(text, entities));
+        self
+    }
+
+    /// Sends a new message with inline `buttons`, row by row.
+    pub fn reply_with_buttons(
+        mut self,
+        text: impl Into<String>,
+        buttons: Vec<Vec<Button>>,
+    ) -> Self {
+        self.actions.push(Action::ReplyButtons(text.into(), buttons));
+        self
+    }
+
+    /// Acknowledges the button press with a notification. Presses are
+    /// acknowledged with nothing once the actions have run otherwise.
+    pub fn answer(mut self, text: impl Into<String>) -> Self {
+        self.actions.push(Action::Answer(text.into()));
+        self
+    }
+
+    /// Sends `upload` with `caption`, e.g. a generated image.
+    pub fn reply_media(mut self, caption: impl Into<String>, upload: Upload) -> Self {
+        self.actions.push(Action::ReplyMedia(caption.into(), upload));
+        self
+    }
+
+    /// Edits the last message this rule sent.
+    pub fn edit(mut self, text: impl Into<String>) -> Self {
+        self.actions.push(Action::Edit(text.into()));
+        self
+    }
+
+    /// Pauses before the next action.
+    pub fn wait(mut self, delay: Duration) -> Self {
+        self.actions.push(Action::Wait(delay));
+        self
+    }
+
+    /// Drops all actions so the bot never answers.
+    pub fn silent(mut self) -> Self {
+        self.actions.clear();
+        self
+    }
+
+    /// Sends replies as plain messages instead of replies to the prompt.
+    pub fn unquoted(mut self) -> Self {
+        self.quote = false;
+        self
+    }
+}
+
+/// A bot living on a [`MemoryTransport`] that answers according to [`Rule`]s.
+///
+/// Prompts matching no rule are ignored. Dropping the bot stops it.
+pub struct FakeBot {
+    transport: MemoryTransport,
+    username: String,
+    id: i64,
+    rules: Arc<Mutex<Vec<Rule>>>,
+    received: Arc<Mutex<Vec<SentMessage>>>,
+    task: JoinHandle<()>,
+}
+
+impl FakeBot {
+    /// Creates a bot on a fresh transport. Must be called within a Tokio
+    /// runtime.
+    pub fn new(username: &str, id: i64) -> Self {
+        Self::on(MemoryTransport::new(), username, id)
+    }
+
+    /// Creates a bot on an existing transport.
+    pub fn on(transport: MemoryTransport, username: &str, id: i64) -> Self {
+        transport.add_user(username, id, PeerKind::Bot);
+
+        let rules: Arc<Mutex<Vec<Rule>>> = Arc::default();
+        let received: Arc<Mutex<Vec<SentMessage>>> = Arc::default();
+
+        let mut outgoing = transport.subscribe();
+        let mut presses = transport.subscribe_presses();
+        let task = {
+            let transport = transport.clone();
+            let rules = rules.clone();
+            let received = received.clone();
+            tokio::spawn(async move {
+                loop {
+                    tokio::select! {
+                        Some(prompt) = outgoing.recv() => {
+                            if prompt.peer.id != id {
+                                continue;
+                            }
+                            received.lock().unwrap().push(prompt.clone());
+
+                            let rule = rules
+                                .lock()
+                                .unwrap()
+                                .iter()
+                                .filter(|rule| rule.pressed.is_none())
+                                .find(|rule| rule.pattern.is_match(&prompt.message.text))
+                                .cloned();
+                            if let Some(rule) = rule {
+                                let reply_to = rule.quote.then_some(prompt.id);
+                                let transport = transport.clone();
+                                tokio::spawn(perform(transport, id, reply_to, None, None, rule));
+                            }
+                        }
+                        Some(press) = presses.recv() => {
+                            if press.message.sender_id != Some(id) {
+                                continue;
+                            }
+                            let rule = rules
+                                .lock()
+                                .unwrap()
+                                .iter()
+                                .find(|rule| rule.pressed.as_ref() == Some(&press.data))
+                                .cloned();
+                            if let Some(rule) = rule {
+                                let reply_to = rule.quote.then_some(press.message.id);
+                                let last = Some(press.message.clone());
+                                let transport = transport.clone();
+                                let press = Some(press);
+                                tokio::spawn(perform(transport, id, reply_to, last, press, rule));
+                            }
+                        }
+                        else => break,
+                    }
+                }
+            })
+        };
+
+        Self {
+            transport,
+            username: username.to_string(),
+            id,
+            rules,
+            received,
+            task,
+        }
+    }
+
+    pub fn add_rule(&self, rule: Rule) {
+        self.rules.lock().unwrap().push(rule);
+    }
+
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    /// A clone of the transport the bot lives on.
+    pub fn transport(&self) -> MemoryTransport {
+        self.transport.clone()
+    }
+
+    /// A config pointing at this bot.
+    pub fn config(&self) -> GrokConfig {
+        GrokConfig::new(1, "test", self.username.clone(), "fake-bot.session")
+    }
+
+    /// Every message the client sent to the bot, in order.
+    pub fn received(&self) -> Vec<SentMessage> {
+        self.received.lock().unwrap().clone()
+    }
+
+    /// Texts of every message the client sent to the bot, in order.
+    pub fn prompts(&self) -> Vec<String> {
+        self.received()
+            .into_iter()
+            .map(|sent| sent.message.text)
+            .collect()
+    }
+
+    /// Sends a message nobody asked for.
+    pub fn say(&self, text: &str) {
+        self.transport.receive(self.id, text, None);
+    }
+}
+
+impl Drop for FakeBot {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn perform(
+    transport: MemoryTransport,
+    bot_id: i64,
+    reply_to: Option<i32>,
+    mut last: Option<IncomingMessage>,
+    mut press: Option<ButtonPress>,
+    rule: Rule,
+) {
+    for action in rule.actions {
+        match action {
+            Action::Wait(delay) => tokio::time::sleep(delay).await,
+            Action::ReplyButtons(text, buttons) => {
+                last = Some(transport.receive_with_buttons(bot_id, &text, buttons, reply_to))
+            }
+            Action::Answer(text) => match press.as_mut() {
+                Some(press) => press.answer(CallbackAnswer {
+                    message: Some(text),
+                    ..Default::default()
+                }),
+                None => log::warn!("FakeBot rule answers without a press: {:?}", rule.pattern),
+            },
+            Action::Reply(text) => last = Some(transport.receive(bot_id, &text, reply_to)),
+            Action::ReplyFormatted(text, entities) => {
+                last = Some(transport.receive_formatted(bot_id, &text, entities, reply_to))
+            }
+            Action::ReplyMedia(caption, upload) => {
+                match transport.receive_upload(bot_id, &caption, &upload, reply_to).await {
+                    Ok(message) => last = Some(message),
+                    Err(e) => log::warn!("FakeBot could not send {:?}: {}", upload, e),
+                }
+            }
+            Action::Edit(text) => match &last {
+                Some(message) => last = Some(transport.edit(message, &text)),
+                None => log::warn!("FakeBot rule edits before replying: {:?}", rule.pattern),
+            },
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+use crate::{lifecycle::Phase, queue::RequestPriority};
+
+/// A token bucket: up to `burst` messages at once, refilled at
+/// `per_second` messages per second.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct RateLimit {
+    pub per_second: f64,
+    #[serde(default = "default_burst")]
+    pub burst: u32,
+}
+
+fn default_burst() -> u32 {
+    1
+}
+
+impl RateLimit {
+    pub fn new(per_second: f64, burst: u32) -> Self {
+        Self { per_second, burst }
+    }
+
+    /// One message every `interval`, as the old `QUEUE_INTERVAL` setting did.
+    pub fn every(interval: Duration) -> Self {
+        Self::new(1.0 / interval.as_secs_f64(), 1)
+    }
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self::every(Duration::from_millis(100))
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    limit: RateLimit,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl Bucket {
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst.max(1) as f64,
+            refilled: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        let capacity = self.limit.burst.max(1) as f64;
+        self.tokens = (self.tokens + elapsed * self.limit.per_second).min(capacity);
+        self.refilled = now;
+    }
+
+    /// How long until a token is available; zero if one is now.
+    fn wait(&self) -> Duration {
+        if self.tokens >= 1.0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64((1.0 - self.tokens) / self.limit.per_second)
+    }
+}
+
+/// How often Telegram has made us wait with `FLOOD_WAIT`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FloodWaitStats {
+    pub count: u64,
+    /// Sum of every wait Telegram asked for.
+    pub total: Duration,
+    /// When sending resumes, while the queue is paused.
+    pub paused_until: Option<Instant>,
+}
+
+/// Decides when the sender may send: the global rate limit, per-priority
+/// limits and any flood wait Telegram has imposed.
+pub(crate) struct Throttle {
+    global: Bucket,
+    per_priority: HashMap<RequestPriority, Bucket>,
+    pub flood_waits: Arc<Mutex<FloodWaitStats>>,
+}
+
+impl Throttle {
+    pub fn new(
+        global: RateLimit,
+        per_priority: &HashMap<RequestPriority, RateLimit>,
+        flood_waits: Arc<Mutex<FloodWaitStats>>,
+    ) -> Self {
+        let now = Instant::now();
+        Self {
+            global: Bucket::new(global, now),
+            per_priority: per_priority
+                .iter()
+                .map(|(priority, limit)| (*priority, Bucket::new(*limit, now)))
+                .collect(),
+            flood_waits,
+        }
+    }
+
+    /// How long until a message of `priority` may be sent.
+    pub fn wait(&mut self, priority: RequestPriority, now: Instant) -> Duration {
+        self.global.refill(now);
+        let own = self.per_priority.get_mut(&priority).map_or(Duration::ZERO, |bucket| {
+            bucket.refill(now);
+            bucket.wait()
+        });
+        own.max(self.global.wait())
+    }
+
+    /// Spends a token for a message of `priority`, which must be ready.
+    pub fn take(&mut self, priority: RequestPriority) {
+        self.global.tokens -= 1.0;
+        if let Some(bucket) = self.per_priority.get_mut(&priority) {
+            bucket.tokens -= 1.0;
+        }
+    }
+
+    pub fn record_flood_wait(&self, wait: Duration, until: Instant) {
+        let mut stats = self.flood_waits.lock().unwrap();
+        stats.count += 1;
+        stats.total += wait;
+        stats.paused_until = Some(until);
+    }
+
+    pub fn flood_wait_over(&self) {
+        self.flood_waits.lock().unwrap().paused_until = None;
+    }
+}
+
+/// Sleeps until `until`, or until the shutdown deadline if that comes first.
+pub(crate) async fn pause(until: Instant, phase: &mut watch::Receiver<Phase>) {
+    loop {
+        let wake = match *phase.borrow() {
+            Phase::Running => until,
+            Phase::Draining { deadline } => until.min(deadline),
+            Phase::Stopped => return,
+        };
+        tokio::select! {
+            _ = tokio::time::sleep_until(wake) => return,
+            changed = phase.changed() => {
+                if changed.is_err() {
+                    tokio::time::sleep_until(until).await;
+                    return;
+                }
+            }
+        }
+    }
+}
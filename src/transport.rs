@@ -0,0 +1,262 @@
+use async_trait::async_trait;
+use std::any::Any;
+use std::time::SystemTime;
+
+use crate::{
+    account::{Account, Authorization},
+    auth::CodeDelivery,
+    error::GrokError,
+    format::{self, Entity},
+    keyboard::{Button, CallbackAnswer},
+    media::{Attachment, Upload},
+};
+
+mod grammers;
+mod memory;
+
+pub use self::grammers::GrammersTransport;
+pub use self::memory::{ButtonPress, MemoryTransport, SentMessage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerKind {
+    User,
+    Bot,
+    Group,
+    Channel,
+}
+
+/// A resolved chat or user that messages can be sent to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Peer {
+    pub id: i64,
+    pub kind: PeerKind,
+    pub access_hash: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OutgoingMessage {
+    /// The message, or the caption when sending media.
+    pub text: String,
+    /// Formatting of `text`.
+    pub entities: Vec<Entity>,
+    pub reply_to: Option<i32>,
+    pub media: Option<Upload>,
+}
+
+impl OutgoingMessage {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Text written in Markdown, sent with Telegram formatting.
+    pub fn markdown(markdown: &str) -> Self {
+        let (text, entities) = format::parse_markdown(markdown);
+        Self {
+            text,
+            entities,
+            ..Default::default()
+        }
+    }
+
+    /// Text written in HTML, sent with Telegram formatting.
+    pub fn html(html: &str) -> Self {
+        let (text, entities) = format::parse_html(html);
+        Self {
+            text,
+            entities,
+            ..Default::default()
+        }
+    }
+
+    /// Attaches `media`, with the text as its caption.
+    pub fn with_media(self, media: Upload) -> Self {
+        Self {
+            media: Some(media),
+            ..self
+        }
+    }
+}
+
+impl From<&str> for OutgoingMessage {
+    fn from(text: &str) -> Self {
+        Self::text(text)
+    }
+}
+
+impl From<&String> for OutgoingMessage {
+    fn from(text: &String) -> Self {
+        Self::text(text.as_str())
+    }
+}
+
+impl From<String> for OutgoingMessage {
+    fn from(text: String) -> Self {
+        Self::text(text)
+    }
+}
+
+/// Media without a caption.
+impl From<Upload> for OutgoingMessage {
+    fn from(media: Upload) -> Self {
+        Self::default().with_media(media)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Photo,
+    Document,
+    Sticker,
+    Voice,
+    Other,
+}
+
+#[derive(Debug, Clone)]
+pub struct IncomingMessage {
+    pub id: i32,
+    pub chat: Peer,
+    pub sender_id: Option<i64>,
+    pub text: String,
+    /// Formatting of `text`.
+    pub entities: Vec<Entity>,
+    pub reply_to: Option<i32>,
+    pub media: Option<Attachment>,
+    /// Inline buttons, row by row.
+    pub buttons: Vec<Vec<Button>>,
+    pub outgoing: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum TransportUpdate {
+    NewMessage(IncomingMessage),
+    MessageEdited(IncomingMessage),
+    /// The QR login token was scanned; exporting it again finishes login.
+    LoginTokenAccepted,
+    /// Anything the client does not act on.
+    Other,
+}
+
+/// Transport-specific state carried between login steps.
+pub struct LoginCode {
+    token: Box<dyn Any + Send + Sync>,
+    delivery: CodeDelivery,
+}
+
+impl LoginCode {
+    pub fn new<T: Any + Send + Sync>(token: T) -> Self {
+        Self {
+            token: Box::new(token),
+            delivery: CodeDelivery::Unknown,
+        }
+    }
+
+    pub fn with_delivery(self, delivery: CodeDelivery) -> Self {
+        Self { delivery, ..self }
+    }
+
+    /// Where Telegram sent the code.
+    pub fn delivery(&self) -> CodeDelivery {
+        self.delivery
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.token.downcast_ref()
+    }
+}
+
+/// Issued by [`TelegramTransport::sign_in`] when the account has 2FA enabled.
+pub struct PasswordChallenge {
+    pub hint: Option<String>,
+    token: Box<dyn Any + Send + Sync>,
+}
+
+impl PasswordChallenge {
+    pub fn new<T: Any + Send + Sync>(hint: Option<String>, token: T) -> Self {
+        Self {
+            hint,
+            token: Box::new(token),
+        }
+    }
+
+    pub fn downcast<T: Any>(self) -> Option<T> {
+        self.token.downcast().ok().map(|token| *token)
+    }
+}
+
+pub enum SignInOutcome {
+    Authorized,
+    PasswordRequired(PasswordChallenge),
+}
+
+/// What [`TelegramTransport::export_login_token`] got back.
+pub enum QrLoginStep {
+    /// Not scanned yet; show `token` until it expires.
+    Token { token: Vec<u8>, expires: SystemTime },
+    Done(SignInOutcome),
+}
+
+/// Everything `GrokClient` needs from Telegram.
+///
+/// [`GrammersTransport`] talks to the real network; [`MemoryTransport`] keeps
+/// everything in memory for tests.
+#[async_trait]
+pub trait TelegramTransport: Send + Sync + 'static {
+    async fn connect(&self) -> Result<(), GrokError>;
+
+    async fn is_authorized(&self) -> Result<bool, GrokError>;
+
+    async fn request_login_code(&self, phone: &str) -> Result<LoginCode, GrokError>;
+
+    async fn sign_in(&self, token: &LoginCode, code: &str) -> Result<SignInOutcome, GrokError>;
+
+    async fn check_password(
+        &self,
+        challenge: PasswordChallenge,
+        password: &str,
+    ) -> Result<(), GrokError>;
+
+    /// Exports a token for QR login, or finishes logging in once the last
+    /// one was scanned.
+    async fn export_login_token(&self) -> Result<QrLoginStep, GrokError> {
+        Err(GrokError::Auth("This transport does not support QR login".into()))
+    }
+
+    async fn get_me(&self) -> Result<Account, GrokError>;
+
+    async fn authorizations(&self) -> Result<Vec<Authorization>, GrokError>;
+
+    async fn terminate_authorization(&self, hash: i64) -> Result<(), GrokError>;
+
+    async fn terminate_other_authorizations(&self) -> Result<(), GrokError>;
+
+    /// Revokes the session and forgets the saved copy.
+    async fn log_out(&self) -> Result<(), GrokError>;
+
+    async fn resolve_username(&self, username: &str) -> Result<Option<Peer>, GrokError>;
+
+    async fn send_message(
+        &self,
+        peer: Peer,
+        message: OutgoingMessage,
+    ) -> Result<IncomingMessage, GrokError>;
+
+    /// Presses the callback button carrying `data` under message
+    /// `message_id` in `chat`, returning how the bot acknowledged it.
+    async fn press_button(
+        &self,
+        chat: Peer,
+        message_id: i32,
+        data: &[u8],
+    ) -> Result<CallbackAnswer, GrokError>;
+
+    async fn next_update(&self) -> Result<TransportUpdate, GrokError>;
+
+    async fn download(&self, attachment: &Attachment) -> Result<Vec<u8>, GrokError>;
+
+    /// Persists the session to wherever the transport keeps it. Does
+    /// nothing after logging out.
+    fn save_session(&self) -> Result<(), GrokError>;
+}
@@ -0,0 +1,512 @@
+use async_trait::async_trait;
+use grammers_client::{
+    grammers_tl_types as tl,
+    types::{media::Document, Attribute, Chat, Downloadable, LoginToken, Media, Message, PasswordToken},
+    Client, Config, InitParams, InputMessage, InvocationError, SignInError, Update,
+};
+use grammers_session::{PackedChat, PackedType, Session};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use super::{
+    IncomingMessage, LoginCode, MediaKind, OutgoingMessage, PasswordChallenge, Peer, PeerKind,
+    QrLoginStep, SignInOutcome, TelegramTransport, TransportUpdate,
+};
+use crate::{
+    account::{Account, Authorization},
+    config::GrokConfig,
+    error::GrokError,
+    format,
+    keyboard::{Button, ButtonKind, CallbackAnswer},
+    media::{Attachment, Upload},
+    session::{FileSessionStore, SessionStore},
+};
+
+/// Talks to Telegram through `grammers`.
+pub struct GrammersTransport {
+    api_id: i32,
+    api_hash: String,
+    store: Arc<dyn SessionStore>,
+    client: RwLock<Option<Client>>,
+}
+
+impl GrammersTransport {
+    /// Keeps the session in `config.session_path`.
+    pub fn new(config: &GrokConfig) -> Self {
+        Self::with_store(config, FileSessionStore::new(&config.session_path))
+    }
+
+    pub fn with_store(config: &GrokConfig, store: impl SessionStore) -> Self {
+        Self::with_shared_store(config, Arc::new(store))
+    }
+
+    pub(crate) fn with_shared_store(config: &GrokConfig, store: Arc<dyn SessionStore>) -> Self {
+        Self {
+            api_id: config.api_id,
+            api_hash: config.api_hash.clone(),
+            store,
+            client: RwLock::new(None),
+        }
+    }
+
+    /// The underlying `grammers` client, once connected.
+    pub fn client(&self) -> Result<Client, GrokError> {
+        self.client
+            .read()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| GrokError::Connection("Not connected".into()))
+    }
+
+    /// Moves the session to `dc_id` by reconnecting. grammers picks the
+    /// datacenter from the session's user, so a placeholder user is stored
+    /// until the login finishes.
+    async fn migrate(&self, dc_id: i32) -> Result<Client, GrokError> {
+        self.client()?.session().set_user(0, dc_id, false);
+        self.connect().await?;
+        self.client()
+    }
+
+    async fn login_token_step(
+        &self,
+        result: Result<tl::enums::auth::LoginToken, InvocationError>,
+    ) -> Result<QrLoginStep, GrokError> {
+        use tl::enums::auth::LoginToken as Token;
+
+        match result {
+            Ok(Token::Token(token)) => Ok(QrLoginStep::Token {
+                token: token.token,
+                expires: unix_time(token.expires),
+            }),
+            Ok(Token::MigrateTo(migrate)) => {
+                let client = self.migrate(migrate.dc_id).await?;
+                let request = tl::functions::auth::ImportLoginToken {
+                    token: migrate.token,
+                };
+                let result = client.invoke(&request).await;
+                Box::pin(self.login_token_step(result)).await
+            }
+            Ok(Token::Success(success)) => match success.authorization {
+                tl::enums::auth::Authorization::Authorization(auth) => {
+                    let client = self.client()?;
+                    let dc_id = client.session().get_user().map_or(DEFAULT_DC, |u| u.dc);
+                    let (id, bot) = match auth.user {
+                        tl::enums::User::User(user) => (user.id, user.bot),
+                        tl::enums::User::Empty(user) => (user.id, false),
+                    };
+                    // grammers only sets up the logged-in state for its own
+                    // sign-in calls, so store the user and reconnect instead
+                    client.session().set_user(id, dc_id, bot);
+                    self.connect().await?;
+                    Ok(QrLoginStep::Done(SignInOutcome::Authorized))
+                }
+                tl::enums::auth::Authorization::SignUpRequired(_) => Err(GrokError::Auth(
+                    "This phone number has no Telegram account".into(),
+                )),
+            },
+            Err(InvocationError::Rpc(e)) if e.name == "SESSION_PASSWORD_NEEDED" => {
+                let tl::enums::account::Password::Password(password) = self
+                    .client()?
+                    .invoke(&tl::functions::account::GetPassword {})
+                    .await?;
+                let hint = password.hint.clone();
+                let challenge = PasswordChallenge::new(hint, PasswordToken::new(password));
+                Ok(QrLoginStep::Done(SignInOutcome::PasswordRequired(challenge)))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Where grammers connects before the session names a datacenter.
+const DEFAULT_DC: i32 = 2;
+
+#[async_trait]
+impl TelegramTransport for GrammersTransport {
+    async fn connect(&self) -> Result<(), GrokError> {
+        // On reconnect, persist the update state first so we resume from it
+        let previous = self.client.read().unwrap().clone();
+        if let Some(previous) = &previous {
+            self.store.save(&previous.session().save())?;
+        }
+
+        let session = match self.store.load()? {
+            Some(data) => Session::load(&data).map_err(|e| GrokError::Session(e.to_string()))?,
+            None => Session::new(),
+        };
+
+        let client = Client::connect(Config {
+            session,
+            api_id: self.api_id,
+            api_hash: self.api_hash.clone(),
+            params: InitParams {
+                catch_up: previous.is_some(),
+                // Surface every FLOOD_WAIT so the sender can pause the queue
+                flood_sleep_threshold: 0,
+                ..Default::default()
+            },
+        })
+            .await
+            .map_err(|e| GrokError::Connection(e.to_string()))?;
+
+        *self.client.write().unwrap() = Some(client);
+        Ok(())
+    }
+
+    async fn is_authorized(&self) -> Result<bool, GrokError> {
+        Ok(self.client()?.is_authorized().await?)
+    }
+
+    async fn request_login_code(&self, phone: &str) -> Result<LoginCode, GrokError> {
+        // grammers keeps how the code was sent to itself, so this stays Unknown
+        let token = self.client()?.request_login_code(phone).await?;
+        Ok(LoginCode::new(token))
+    }
+
+    async fn sign_in(&self, token: &LoginCode, code: &str) -> Result<SignInOutcome, GrokError> {
+        let token = token
+            .downcast_ref::<LoginToken>()
+            .ok_or_else(|| GrokError::Auth("Login code was issued by another transport".into()))?;
+
+        match self.client()?.sign_in(token, code).await {
+            Ok(_) => Ok(SignInOutcome::Authorized),
+            Err(SignInError::PasswordRequired(token)) => {
+                let hint = token.hint().map(str::to_string);
+                Ok(SignInOutcome::PasswordRequired(PasswordChallenge::new(hint, token)))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn check_password(
+        &self,
+        challenge: PasswordChallenge,
+        password: &str,
+    ) -> Result<(), GrokError> {
+        let token = challenge
+            .downcast::<PasswordToken>()
+            .ok_or_else(|| GrokError::Auth("Password challenge was issued by another transport".into()))?;
+        self.client()?.check_password(token, password).await?;
+        Ok(())
+    }
+
+    async fn export_login_token(&self) -> Result<QrLoginStep, GrokError> {
+        let request = tl::functions::auth::ExportLoginToken {
+            api_id: self.api_id,
+            api_hash: self.api_hash.clone(),
+            except_ids: Vec::new(),
+        };
+        let result = self.client()?.invoke(&request).await;
+        self.login_token_step(result).await
+    }
+
+    async fn get_me(&self) -> Result<Account, GrokError> {
+        let me = self.client()?.get_me().await?;
+        Ok(Account {
+            id: me.id(),
+            first_name: me.first_name().to_string(),
+            last_name: me.last_name().map(str::to_string),
+            username: me.username().map(str::to_string),
+            phone: me.phone().map(str::to_string),
+            bot: me.is_bot(),
+        })
+    }
+
+    async fn authorizations(&self) -> Result<Vec<Authorization>, GrokError> {
+        let tl::enums::account::Authorizations::Authorizations(list) = self
+            .client()?
+            .invoke(&tl::functions::account::GetAuthorizations {})
+            .await?;
+        Ok(list
+            .authorizations
+            .into_iter()
+            .map(|tl::enums::Authorization::Authorization(auth)| Authorization {
+                hash: auth.hash,
+                current: auth.current,
+                device_model: auth.device_model,
+                platform: auth.platform,
+                system_version: auth.system_version,
+                app_name: auth.app_name,
+                app_version: auth.app_version,
+                ip: auth.ip,
+                country: auth.country,
+                created: unix_time(auth.date_created),
+                last_active: unix_time(auth.date_active),
+            })
+            .collect())
+    }
+
+    async fn terminate_authorization(&self, hash: i64) -> Result<(), GrokError> {
+        let request = tl::functions::account::ResetAuthorization { hash };
+        self.client()?.invoke(&request).await?;
+        Ok(())
+    }
+
+    async fn terminate_other_authorizations(&self) -> Result<(), GrokError> {
+        self.client()?
+            .invoke(&tl::functions::auth::ResetAuthorizations {})
+            .await?;
+        Ok(())
+    }
+
+    async fn log_out(&self) -> Result<(), GrokError> {
+        self.client()?.sign_out().await?;
+        // Forget the client so nothing saves its revoked key again
+        *self.client.write().unwrap() = None;
+        self.store.clear()
+    }
+
+    async fn resolve_username(&self, username: &str) -> Result<Option<Peer>, GrokError> {
+        let chat = self.client()?.resolve_username(username).await?;
+        Ok(chat.map(|chat| peer_from_chat(&chat)))
+    }
+
+    async fn send_message(
+        &self,
+        peer: Peer,
+        message: OutgoingMessage,
+    ) -> Result<IncomingMessage, GrokError> {
+        let client = self.client()?;
+        let mut input = InputMessage::text(message.text)
+            .fmt_entities(format::to_tl(&message.entities))
+            .reply_to(message.reply_to);
+        if let Some(upload) = &message.media {
+            input = attach(&client, input, upload).await?;
+        }
+        let sent = client.send_message(packed_chat(peer), input).await?;
+        Ok(incoming_from_message(&sent))
+    }
+
+    async fn press_button(
+        &self,
+        chat: Peer,
+        message_id: i32,
+        data: &[u8],
+    ) -> Result<CallbackAnswer, GrokError> {
+        let request = tl::functions::messages::GetBotCallbackAnswer {
+            game: false,
+            peer: packed_chat(chat).to_input_peer(),
+            msg_id: message_id,
+            data: Some(data.to_vec()),
+            password: None,
+        };
+        let tl::enums::messages::BotCallbackAnswer::Answer(answer) =
+            self.client()?.invoke(&request).await?;
+        Ok(CallbackAnswer {
+            message: answer.message.filter(|message| !message.is_empty()),
+            alert: answer.alert,
+            url: answer.url,
+        })
+    }
+
+    async fn next_update(&self) -> Result<TransportUpdate, GrokError> {
+        let update = self.client()?.next_update().await?;
+        Ok(match update {
+            Update::NewMessage(message) => TransportUpdate::NewMessage(incoming_from_message(&message)),
+            Update::MessageEdited(message) => {
+                TransportUpdate::MessageEdited(incoming_from_message(&message))
+            }
+            Update::Raw(tl::enums::Update::LoginToken) => TransportUpdate::LoginTokenAccepted,
+            _ => TransportUpdate::Other,
+        })
+    }
+
+    async fn download(&self, attachment: &Attachment) -> Result<Vec<u8>, GrokError> {
+        let media = attachment
+            .downcast_ref::<Media>()
+            .filter(|_| attachment.kind != MediaKind::Other)
+            .ok_or_else(|| GrokError::Bot(format!("{:?} cannot be downloaded", attachment)))?;
+
+        let mut download = self
+            .client()?
+            .iter_download(&Downloadable::Media(media.clone()));
+        let mut data = Vec::with_capacity(attachment.size as usize);
+        while let Some(chunk) = download.next().await? {
+            data.extend_from_slice(&chunk);
+        }
+        Ok(data)
+    }
+
+    fn save_session(&self) -> Result<(), GrokError> {
+        match self.client.read().unwrap().as_ref() {
+            Some(client) => self.store.save(&client.session().save()),
+            None => Ok(()),
+        }
+    }
+}
+
+fn unix_time(secs: i32) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64)
+}
+
+fn peer_from_chat(chat: &Chat) -> Peer {
+    let packed = chat.pack();
+    let kind = match packed.ty {
+        PackedType::User => PeerKind::User,
+        PackedType::Bot => PeerKind::Bot,
+        PackedType::Chat => PeerKind::Group,
+        PackedType::Megagroup | PackedType::Broadcast | PackedType::Gigagroup => PeerKind::Channel,
+    };
+    Peer {
+        id: packed.id,
+        kind,
+        access_hash: packed.access_hash,
+    }
+}
+
+fn packed_chat(peer: Peer) -> PackedChat {
+    let ty = match peer.kind {
+        PeerKind::User => PackedType::User,
+        PeerKind::Bot => PackedType::Bot,
+        PeerKind::Group => PackedType::Chat,
+        PeerKind::Channel => PackedType::Broadcast,
+    };
+    PackedChat {
+        ty,
+        id: peer.id,
+        access_hash: peer.access_hash,
+    }
+}
+
+fn incoming_from_message(message: &Message) -> IncomingMessage {
+    IncomingMessage {
+        id: message.id(),
+        chat: peer_from_chat(&message.chat()),
+        sender_id: message.sender().map(|s| s.id()),
+        text: message.text().to_string(),
+        entities: message
+            .fmt_entities()
+            .map(|entities| entities.iter().map(format::from_tl).collect())
+            .unwrap_or_default(),
+        reply_to: message.reply_to_message_id(),
+        media: message.media().and_then(attachment_from_media),
+        buttons: message.reply_markup().map(buttons_from_markup).unwrap_or_default(),
+        outgoing: message.outgoing(),
+    }
+}
+
+/// Inline keyboards only; reply keyboards replace the user's keyboard and
+/// cannot be pressed by us.
+fn buttons_from_markup(markup: tl::enums::ReplyMarkup) -> Vec<Vec<Button>> {
+    use tl::enums::KeyboardButton as KB;
+
+    let tl::enums::ReplyMarkup::ReplyInlineMarkup(markup) = markup else {
+        return Vec::new();
+    };
+    markup
+        .rows
+        .into_iter()
+        .map(|tl::enums::KeyboardButtonRow::Row(row)| {
+            row.buttons
+                .into_iter()
+                .map(|button| match button {
+                    KB::Callback(b) => Button::callback(b.text, b.data),
+                    KB::Url(b) => Button::url(b.text, b.url),
+                    KB::UrlAuth(b) => Button::url(b.text, b.url),
+                    KB::WebView(b) => Button::url(b.text, b.url),
+                    KB::SimpleWebView(b) => Button::url(b.text, b.url),
+                    other => Button {
+                        text: button_text(other),
+                        kind: ButtonKind::Other,
+                    },
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn button_text(button: tl::enums::KeyboardButton) -> String {
+    use tl::enums::KeyboardButton as KB;
+
+    match button {
+        KB::Button(b) => b.text,
+        KB::Url(b) => b.text,
+        KB::Callback(b) => b.text,
+        KB::RequestPhone(b) => b.text,
+        KB::RequestGeoLocation(b) => b.text,
+        KB::SwitchInline(b) => b.text,
+        KB::Game(b) => b.text,
+        KB::Buy(b) => b.text,
+        KB::UrlAuth(b) => b.text,
+        KB::InputKeyboardButtonUrlAuth(b) => b.text,
+        KB::RequestPoll(b) => b.text,
+        KB::InputKeyboardButtonUserProfile(b) => b.text,
+        KB::UserProfile(b) => b.text,
+        KB::WebView(b) => b.text,
+        KB::SimpleWebView(b) => b.text,
+        KB::RequestPeer(b) => b.text,
+        KB::InputKeyboardButtonRequestPeer(b) => b.text,
+    }
+}
+
+async fn attach(
+    client: &Client,
+    input: InputMessage,
+    upload: &Upload,
+) -> Result<InputMessage, GrokError> {
+    let uploaded = match upload.path() {
+        Some(path) => client.upload_file(path).await?,
+        None => {
+            let data = upload.read().await?;
+            let mut stream = std::io::Cursor::new(&data);
+            client
+                .upload_stream(&mut stream, data.len(), upload.name().to_string())
+                .await?
+        }
+    };
+
+    // The MIME type is picked when the document is attached
+    let input = match upload.mime_type() {
+        Some(mime_type) => input.mime_type(mime_type),
+        None => input,
+    };
+    Ok(match upload.kind() {
+        MediaKind::Photo => input.photo(uploaded),
+        MediaKind::Voice => input.document(uploaded).attribute(Attribute::Voice {
+            duration: upload.duration().unwrap_or_default(),
+            waveform: None,
+        }),
+        _ => input.document(uploaded),
+    })
+}
+
+fn attachment_from_media(media: Media) -> Option<Attachment> {
+    let (kind, mime_type, size, file_name) = match &media {
+        Media::Photo(photo) => (MediaKind::Photo, Some("image/jpeg"), photo.size(), None),
+        Media::Document(document) => {
+            let kind = if is_voice(document) {
+                MediaKind::Voice
+            } else {
+                MediaKind::Document
+            };
+            let name = Some(document.name()).filter(|name| !name.is_empty());
+            (kind, document.mime_type(), document.size(), name)
+        }
+        Media::Sticker(sticker) => (
+            MediaKind::Sticker,
+            sticker.document.mime_type(),
+            sticker.document.size(),
+            None,
+        ),
+        // Link previews are not attachments
+        Media::WebPage(_) => return None,
+        _ => (MediaKind::Other, None, 0, None),
+    };
+
+    let mime_type = mime_type.map(str::to_string);
+    let file_name = file_name.map(str::to_string);
+    let mut attachment = Attachment::new(kind, media);
+    attachment.mime_type = mime_type;
+    attachment.size = size.max(0) as u64;
+    attachment.file_name = file_name;
+    Some(attachment)
+}
+
+fn is_voice(document: &Document) -> bool {
+    match &document.raw.document {
+        Some(tl::enums::Document::Document(document)) => document.attributes.iter().any(|attr| {
+            matches!(attr, tl::enums::DocumentAttribute::Audio(audio) if audio.voice)
+        }),
+        _ => false,
+    }
+}
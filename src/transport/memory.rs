@@ -0,0 +1,618 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::sync::{mpsc, oneshot, watch};
+
+use super::{
+    IncomingMessage, LoginCode, OutgoingMessage, PasswordChallenge, Peer, PeerKind, QrLoginStep,
+    SignInOutcome, TelegramTransport, TransportUpdate,
+};
+use crate::{
+    account::{Account, Authorization},
+    auth::CodeDelivery,
+    error::GrokError,
+    format::Entity,
+    keyboard::{Button, CallbackAnswer},
+    media::{Attachment, Upload},
+};
+
+/// A message the client sent through a [`MemoryTransport`].
+#[derive(Debug, Clone)]
+pub struct SentMessage {
+    pub id: i32,
+    pub peer: Peer,
+    pub message: OutgoingMessage,
+}
+
+/// What a received message carries besides its text.
+#[derive(Default)]
+struct Extras {
+    entities: Vec<Entity>,
+    buttons: Vec<Vec<Button>>,
+    media: Option<Attachment>,
+}
+
+/// A button the client pressed on a [`MemoryTransport`], waiting for the
+/// bot's answer.
+#[derive(Debug)]
+pub struct ButtonPress {
+    /// The message the button is under.
+    pub message: IncomingMessage,
+    pub data: Vec<u8>,
+    answer: Option<oneshot::Sender<CallbackAnswer>>,
+}
+
+impl ButtonPress {
+    /// Acknowledges the press. Dropping it unanswered answers with nothing.
+    pub fn answer(&mut self, answer: CallbackAnswer) {
+        if let Some(tx) = self.answer.take() {
+            let _ = tx.send(answer);
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    authorized: bool,
+    login_code: Option<String>,
+    password: Option<String>,
+    users: HashMap<String, Peer>,
+    sent: Vec<SentMessage>,
+    subscribers: Vec<mpsc::UnboundedSender<SentMessage>>,
+    press_subscribers: Vec<mpsc::UnboundedSender<ButtonPress>>,
+    // Latest version of every message delivered with buttons.
+    keyboards: HashMap<i32, IncomingMessage>,
+    last_message_id: i32,
+    failing_connects: u32,
+    connects: u32,
+    flood_wait: Option<Duration>,
+    session_saves: u32,
+    qr_tokens: u32,
+    qr_scanned: bool,
+    me: Option<Account>,
+    authorizations: Vec<Authorization>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    updates_tx: mpsc::UnboundedSender<TransportUpdate>,
+    updates_rx: tokio::sync::Mutex<mpsc::UnboundedReceiver<TransportUpdate>>,
+    connected: watch::Sender<bool>,
+}
+
+/// In-memory stand-in for Telegram, for tests.
+///
+/// Clones share the same state, so a test can keep one clone to drive the
+/// conversation while the client owns another.
+#[derive(Clone)]
+pub struct MemoryTransport {
+    shared: Arc<Shared>,
+}
+
+impl Default for MemoryTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryTransport {
+    /// An already authorized transport with no known users.
+    pub fn new() -> Self {
+        let (updates_tx, updates_rx) = mpsc::unbounded_channel();
+        let state = State {
+            authorized: true,
+            me: Some(Account {
+                id: 1000,
+                first_name: "Test".into(),
+                last_name: None,
+                username: Some("grok_test_user".into()),
+                phone: Some("+10000000000".into()),
+                bot: false,
+            }),
+            authorizations: vec![authorization(1, true, "grok-client")],
+            ..Default::default()
+        };
+        Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(state),
+                updates_tx,
+                updates_rx: tokio::sync::Mutex::new(updates_rx),
+                connected: watch::Sender::new(false),
+            }),
+        }
+    }
+
+    /// Requires signing in with `code` (and `password`, if given) first.
+    pub fn require_login(&self, code: &str, password: Option<&str>) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.authorized = false;
+        state.login_code = Some(code.to_string());
+        state.password = password.map(str::to_string);
+    }
+
+    /// Scans the current QR login token, as a logged-in phone would.
+    pub fn scan_qr(&self) {
+        self.shared.state.lock().unwrap().qr_scanned = true;
+        self.push_update(TransportUpdate::LoginTokenAccepted);
+    }
+
+    /// How many QR login tokens have been exported.
+    pub fn qr_tokens(&self) -> u32 {
+        self.shared.state.lock().unwrap().qr_tokens
+    }
+
+    /// The account [`TelegramTransport::get_me`] returns.
+    pub fn set_me(&self, account: Account) {
+        self.shared.state.lock().unwrap().me = Some(account);
+    }
+
+    /// Logs the account in on another device. Returns the session's hash.
+    pub fn add_authorization(&self, device_model: &str) -> i64 {
+        let mut state = self.shared.state.lock().unwrap();
+        let hash = state.authorizations.iter().map(|a| a.hash).max().unwrap_or(0) + 1;
+        state
+            .authorizations
+            .push(authorization(hash, false, device_model));
+        hash
+    }
+
+    pub fn is_logged_in(&self) -> bool {
+        self.shared.state.lock().unwrap().authorized
+    }
+
+    /// Drops the connection: pending and later calls fail with
+    /// [`GrokError::Connection`] until the client connects again. Updates
+    /// pushed meanwhile are delivered after that.
+    pub fn disconnect(&self) {
+        self.shared.connected.send_replace(false);
+    }
+
+    pub fn is_connected(&self) -> bool {
+        *self.shared.connected.borrow()
+    }
+
+    /// Makes the next `count` connection attempts fail.
+    pub fn fail_connects(&self, count: u32) {
+        self.shared.state.lock().unwrap().failing_connects = count;
+    }
+
+    /// How many times the client has tried to connect.
+    pub fn connect_count(&self) -> u32 {
+        self.shared.state.lock().unwrap().connects
+    }
+
+    /// Makes the next send fail with [`GrokError::FloodWait`].
+    pub fn flood_wait(&self, wait: Duration) {
+        self.shared.state.lock().unwrap().flood_wait = Some(wait);
+    }
+
+    /// How many times the client has saved its session.
+    pub fn session_saves(&self) -> u32 {
+        self.shared.state.lock().unwrap().session_saves
+    }
+
+    fn ensure_authorized(&self) -> Result<(), GrokError> {
+        self.ensure_connected()?;
+        if self.is_logged_in() {
+            Ok(())
+        } else {
+            Err(GrokError::Invocation("AUTH_KEY_UNREGISTERED".into()))
+        }
+    }
+
+    fn ensure_connected(&self) -> Result<(), GrokError> {
+        if self.is_connected() {
+            Ok(())
+        } else {
+            Err(GrokError::Connection("Not connected".into()))
+        }
+    }
+
+    /// Makes `username` resolvable. Returns the peer it resolves to.
+    pub fn add_user(&self, username: &str, id: i64, kind: PeerKind) -> Peer {
+        let peer = self.peer(id, kind);
+        let mut state = self.shared.state.lock().unwrap();
+        state.users.insert(username.to_lowercase(), peer);
+        peer
+    }
+
+    /// The peer `id` would resolve to on this transport.
+    pub fn peer(&self, id: i64, kind: PeerKind) -> Peer {
+        Peer {
+            id,
+            kind,
+            access_hash: Some(id ^ 0x5eed),
+        }
+    }
+
+    fn peer_kind(&self, id: i64) -> Option<PeerKind> {
+        let state = self.shared.state.lock().unwrap();
+        state.users.values().find(|p| p.id == id).map(|p| p.kind)
+    }
+
+    /// Every message sent so far.
+    pub fn sent(&self) -> Vec<SentMessage> {
+        self.shared.state.lock().unwrap().sent.clone()
+    }
+
+    /// Receives every message sent from now on.
+    pub fn subscribe(&self) -> mpsc::UnboundedReceiver<SentMessage> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.shared.state.lock().unwrap().subscribers.push(tx);
+        rx
+    }
+
+    /// Receives every button pressed from now on. The first subscriber
+    /// still listening gets to answer.
+    pub fn subscribe_presses(&self) -> mpsc::UnboundedReceiver<ButtonPress> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.shared.state.lock().unwrap().press_subscribers.push(tx);
+        rx
+    }
+
+    /// Allocates the next message id, shared by both directions as in a
+    /// Telegram private chat.
+    pub fn next_message_id(&self) -> i32 {
+        let mut state = self.shared.state.lock().unwrap();
+        state.last_message_id += 1;
+        state.last_message_id
+    }
+
+    /// Delivers `text` from `from` in its private chat with us.
+    pub fn receive(&self, from: i64, text: &str, reply_to: Option<i32>) -> IncomingMessage {
+        self.receive_in(self.private_chat(from), from, text, reply_to)
+    }
+
+    /// Delivers `text` from user `from` in `chat`.
+    pub fn receive_in(
+        &self,
+        chat: Peer,
+        from: i64,
+        text: &str,
+        reply_to: Option<i32>,
+    ) -> IncomingMessage {
+        self.receive_with(chat, from, text, reply_to, Extras::default())
+    }
+
+    /// Delivers `text` with formatting `entities` from `from` in its private
+    /// chat with us.
+    pub fn receive_formatted(
+        &self,
+        from: i64,
+        text: &str,
+        entities: Vec<Entity>,
+        reply_to: Option<i32>,
+    ) -> IncomingMessage {
+        let extras = Extras {
+            entities,
+            ..Extras::default()
+        };
+        self.receive_with(self.private_chat(from), from, text, reply_to, extras)
+    }
+
+    /// Delivers `text` with inline `buttons` from `from` in its private
+    /// chat with us.
+    pub fn receive_with_buttons(
+        &self,
+        from: i64,
+        text: &str,
+        buttons: Vec<Vec<Button>>,
+        reply_to: Option<i32>,
+    ) -> IncomingMessage {
+        let extras = Extras {
+            buttons,
+            ..Extras::default()
+        };
+        self.receive_with(self.private_chat(from), from, text, reply_to, extras)
+    }
+
+    /// Delivers an edit of a message received earlier. The message keeps
+    /// its buttons.
+    pub fn edit(&self, message: &IncomingMessage, text: &str) -> IncomingMessage {
+        let edited = IncomingMessage {
+            text: text.to_string(),
+            entities: Vec::new(),
+            ..message.clone()
+        };
+        self.deliver(TransportUpdate::MessageEdited(edited.clone()));
+        edited
+    }
+
+    /// Delivers `upload` with `caption` from `from` in its private chat
+    /// with us. Fails if `upload` is a file that cannot be read.
+    pub async fn receive_upload(
+        &self,
+        from: i64,
+        caption: &str,
+        upload: &Upload,
+        reply_to: Option<i32>,
+    ) -> Result<IncomingMessage, GrokError> {
+        let extras = Extras {
+            media: Some(attachment(upload).await?),
+            ..Extras::default()
+        };
+        Ok(self.receive_with(self.private_chat(from), from, caption, reply_to, extras))
+    }
+
+    fn private_chat(&self, with: i64) -> Peer {
+        let kind = self.peer_kind(with).unwrap_or(PeerKind::User);
+        self.peer(with, kind)
+    }
+
+    /// Delivers a new message from user `from` in `chat`.
+    fn receive_with(
+        &self,
+        chat: Peer,
+        from: i64,
+        text: &str,
+        reply_to: Option<i32>,
+        extras: Extras,
+    ) -> IncomingMessage {
+        let message = IncomingMessage {
+            id: self.next_message_id(),
+            chat,
+            sender_id: Some(from),
+            text: text.to_string(),
+            entities: extras.entities,
+            reply_to,
+            media: extras.media,
+            buttons: extras.buttons,
+            outgoing: false,
+        };
+        self.deliver(TransportUpdate::NewMessage(message.clone()));
+        message
+    }
+
+    pub fn push_update(&self, update: TransportUpdate) {
+        let _ = self.shared.updates_tx.send(update);
+    }
+
+    /// Pushes a message update, remembering its buttons so they can be
+    /// pressed.
+    fn deliver(&self, update: TransportUpdate) {
+        if let TransportUpdate::NewMessage(message) | TransportUpdate::MessageEdited(message) =
+            &update
+        {
+            if !message.buttons.is_empty() {
+                let mut state = self.shared.state.lock().unwrap();
+                state.keyboards.insert(message.id, message.clone());
+            }
+        }
+        self.push_update(update);
+    }
+}
+
+#[async_trait]
+impl TelegramTransport for MemoryTransport {
+    async fn connect(&self) -> Result<(), GrokError> {
+        {
+            let mut state = self.shared.state.lock().unwrap();
+            state.connects += 1;
+            if state.failing_connects > 0 {
+                state.failing_connects -= 1;
+                return Err(GrokError::Connection("Connection refused".into()));
+            }
+        }
+        self.shared.connected.send_replace(true);
+        Ok(())
+    }
+
+    async fn is_authorized(&self) -> Result<bool, GrokError> {
+        Ok(self.is_logged_in())
+    }
+
+    async fn request_login_code(&self, phone: &str) -> Result<LoginCode, GrokError> {
+        Ok(LoginCode::new(phone.to_string()).with_delivery(CodeDelivery::App))
+    }
+
+    async fn sign_in(&self, _token: &LoginCode, code: &str) -> Result<SignInOutcome, GrokError> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.login_code.as_deref() != Some(code) {
+            return Err(GrokError::Auth("Invalid code".into()));
+        }
+        if state.password.is_some() {
+            return Ok(SignInOutcome::PasswordRequired(PasswordChallenge::new(None, ())));
+        }
+        state.authorized = true;
+        Ok(SignInOutcome::Authorized)
+    }
+
+    async fn check_password(
+        &self,
+        _challenge: PasswordChallenge,
+        password: &str,
+    ) -> Result<(), GrokError> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.password.as_deref() != Some(password) {
+            return Err(GrokError::Auth("Invalid password".into()));
+        }
+        state.authorized = true;
+        Ok(())
+    }
+
+    async fn export_login_token(&self) -> Result<QrLoginStep, GrokError> {
+        let mut state = self.shared.state.lock().unwrap();
+        if !state.qr_scanned {
+            state.qr_tokens += 1;
+            return Ok(QrLoginStep::Token {
+                token: format!("token-{}", state.qr_tokens).into_bytes(),
+                expires: SystemTime::now() + Duration::from_secs(30),
+            });
+        }
+        if state.password.is_some() {
+            let challenge = PasswordChallenge::new(None, ());
+            return Ok(QrLoginStep::Done(SignInOutcome::PasswordRequired(challenge)));
+        }
+        state.authorized = true;
+        Ok(QrLoginStep::Done(SignInOutcome::Authorized))
+    }
+
+    async fn get_me(&self) -> Result<Account, GrokError> {
+        self.ensure_authorized()?;
+        let state = self.shared.state.lock().unwrap();
+        Ok(state.me.clone().expect("set in new"))
+    }
+
+    async fn authorizations(&self) -> Result<Vec<Authorization>, GrokError> {
+        self.ensure_authorized()?;
+        Ok(self.shared.state.lock().unwrap().authorizations.clone())
+    }
+
+    async fn terminate_authorization(&self, hash: i64) -> Result<(), GrokError> {
+        self.ensure_authorized()?;
+        let mut state = self.shared.state.lock().unwrap();
+        let position = state
+            .authorizations
+            .iter()
+            .position(|a| a.hash == hash && !a.current)
+            .ok_or_else(|| GrokError::Invocation("HASH_INVALID".into()))?;
+        state.authorizations.remove(position);
+        Ok(())
+    }
+
+    async fn terminate_other_authorizations(&self) -> Result<(), GrokError> {
+        self.ensure_authorized()?;
+        let mut state = self.shared.state.lock().unwrap();
+        state.authorizations.retain(|a| a.current);
+        Ok(())
+    }
+
+    async fn log_out(&self) -> Result<(), GrokError> {
+        self.ensure_authorized()?;
+        let mut state = self.shared.state.lock().unwrap();
+        state.authorized = false;
+        state.authorizations.retain(|a| !a.current);
+        Ok(())
+    }
+
+    async fn resolve_username(&self, username: &str) -> Result<Option<Peer>, GrokError> {
+        let state = self.shared.state.lock().unwrap();
+        Ok(state.users.get(&username.to_lowercase()).copied())
+    }
+
+    async fn send_message(
+        &self,
+        peer: Peer,
+        message: OutgoingMessage,
+    ) -> Result<IncomingMessage, GrokError> {
+        self.ensure_connected()?;
+        if let Some(wait) = self.shared.state.lock().unwrap().flood_wait.take() {
+            return Err(GrokError::FloodWait(wait));
+        }
+        let media = match &message.media {
+            Some(upload) => Some(attachment(upload).await?),
+            None => None,
+        };
+        let id = self.next_message_id();
+        let sent = SentMessage {
+            id,
+            peer,
+            message: message.clone(),
+        };
+
+        let mut state = self.shared.state.lock().unwrap();
+        state.sent.push(sent.clone());
+        state.subscribers.retain(|tx| tx.send(sent.clone()).is_ok());
+
+        Ok(IncomingMessage {
+            id,
+            chat: peer,
+            sender_id: None,
+            text: message.text,
+            entities: message.entities,
+            reply_to: message.reply_to,
+            media,
+            buttons: Vec::new(),
+            outgoing: true,
+        })
+    }
+
+    async fn press_button(
+        &self,
+        chat: Peer,
+        message_id: i32,
+        data: &[u8],
+    ) -> Result<CallbackAnswer, GrokError> {
+        self.ensure_authorized()?;
+        let (tx, rx) = oneshot::channel();
+        {
+            let mut state = self.shared.state.lock().unwrap();
+            let message = state
+                .keyboards
+                .get(&message_id)
+                .filter(|message| message.chat.id == chat.id)
+                .cloned()
+                .ok_or_else(|| GrokError::Invocation("MESSAGE_ID_INVALID".into()))?;
+            let known = message.buttons.iter().flatten().any(|b| b.data() == Some(data));
+            if !known {
+                return Err(GrokError::Invocation("DATA_INVALID".into()));
+            }
+
+            let press = ButtonPress {
+                message,
+                data: data.to_vec(),
+                answer: Some(tx),
+            };
+            state.press_subscribers.retain(|tx| !tx.is_closed());
+            if let Some(subscriber) = state.press_subscribers.first() {
+                let _ = subscriber.send(press);
+            }
+        }
+        // A press nobody answered counts as an empty answer
+        Ok(rx.await.unwrap_or_default())
+    }
+
+    async fn next_update(&self) -> Result<TransportUpdate, GrokError> {
+        let mut connected = self.shared.connected.subscribe();
+        let mut updates = self.shared.updates_rx.lock().await;
+        self.ensure_connected()?;
+        tokio::select! {
+            _ = connected.wait_for(|c| !c) => Err(GrokError::Connection("Connection lost".into())),
+            update = updates.recv() => match update {
+                Some(update) => Ok(update),
+                // We hold the sender, so this never happens.
+                None => Err(GrokError::Connection("Update channel closed".into())),
+            },
+        }
+    }
+
+    async fn download(&self, attachment: &Attachment) -> Result<Vec<u8>, GrokError> {
+        attachment
+            .downcast_ref::<Vec<u8>>()
+            .cloned()
+            .ok_or_else(|| GrokError::Bot("Attachment is from another transport".into()))
+    }
+
+    fn save_session(&self) -> Result<(), GrokError> {
+        self.shared.state.lock().unwrap().session_saves += 1;
+        Ok(())
+    }
+}
+
+fn authorization(hash: i64, current: bool, device_model: &str) -> Authorization {
+    Authorization {
+        hash,
+        current,
+        device_model: device_model.to_string(),
+        platform: "Linux".into(),
+        system_version: String::new(),
+        app_name: "grok-client".into(),
+        app_version: env!("CARGO_PKG_VERSION").into(),
+        ip: "127.0.0.1".into(),
+        country: String::new(),
+        created: SystemTime::UNIX_EPOCH,
+        last_active: SystemTime::UNIX_EPOCH,
+    }
+}
+
+/// What `upload` looks like once Telegram has it.
+async fn attachment(upload: &Upload) -> Result<Attachment, GrokError> {
+    let data = upload.read().await?;
+    let size = data.len() as u64;
+    let mut attachment = Attachment::new(upload.kind(), data);
+    attachment.mime_type = upload.mime_type().map(str::to_string);
+    attachment.size = size;
+    attachment.file_name = Some(upload.name().to_string()).filter(|name| !name.is_empty());
+    Ok(attachment)
+}
@@ -0,0 +1,66 @@
+use grok_client::account::Account;
+use grok_client::testing::FakeBot;
+use grok_client::ConnectionState;
+
+mod common;
+use common::{connect, BOT_ID};
+
+#[tokio::test]
+async fn get_me_returns_the_logged_in_account() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let account = Account {
+        id: 7,
+        first_name: "Ops".into(),
+        last_name: Some("Robot".into()),
+        username: Some("ops_robot".into()),
+        phone: None,
+        bot: false,
+    };
+    bot.transport().set_me(account.clone());
+    let client = connect(&bot).await;
+
+    assert_eq!(client.get_me().await.unwrap(), account);
+}
+
+#[tokio::test]
+async fn other_sessions_can_be_listed_and_terminated() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let phone = bot.transport().add_authorization("Pixel 8");
+    bot.transport().add_authorization("MacBook");
+    bot.transport().add_authorization("iPad");
+    let client = connect(&bot).await;
+
+    let sessions = client.authorizations().await.unwrap();
+    assert_eq!(sessions.len(), 4);
+    let current: Vec<_> = sessions.iter().filter(|a| a.current).collect();
+    assert_eq!(current.len(), 1);
+
+    client.terminate_authorization(phone).await.unwrap();
+    let devices: Vec<_> = client
+        .authorizations()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|a| a.device_model)
+        .collect();
+    assert!(!devices.contains(&"Pixel 8".to_string()), "{devices:?}");
+
+    // The client's own session is ended with log_out instead
+    assert!(client.terminate_authorization(current[0].hash).await.is_err());
+
+    client.terminate_other_authorizations().await.unwrap();
+    let sessions = client.authorizations().await.unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+}
+
+#[tokio::test]
+async fn log_out_revokes_the_session() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = connect(&bot).await;
+
+    client.log_out().await.unwrap();
+    assert!(!bot.transport().is_logged_in());
+    assert_eq!(*client.connection_state().borrow(), ConnectionState::AuthRequired);
+    assert!(client.get_me().await.is_err());
+}
@@ -0,0 +1,221 @@
+use grok_client::auth::{AuthQuestion, ChannelPrompter, CodeDelivery, EnvPrompter, LoginMethod};
+use grok_client::prelude::*;
+use grok_client::testing::FakeBot;
+use std::time::Duration;
+
+mod common;
+use common::BOT_ID;
+
+#[tokio::test]
+async fn a_channel_prompter_answers_each_question() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.transport().require_login("12345", Some("hunter2"));
+    let (prompter, mut questions) = ChannelPrompter::new();
+
+    let ui = tokio::spawn(async move {
+        let mut asked = Vec::new();
+        while let Some(request) = questions.next().await {
+            let answer = match &request.question {
+                AuthQuestion::Phone => "+1234567890",
+                AuthQuestion::Code(_) => "12345",
+                AuthQuestion::Password { .. } => "hunter2",
+                AuthQuestion::Qr(_) => unreachable!("not logging in by QR code"),
+            };
+            asked.push(request.question.clone());
+            request.answer(answer);
+        }
+        asked
+    });
+
+    let client = GrokClient::builder(bot.config())
+        .transport(bot.transport())
+        .prompter(prompter)
+        .build()
+        .await;
+    assert!(client.is_ok());
+    assert!(bot.transport().is_logged_in());
+
+    drop(client);
+    assert_eq!(
+        ui.await.unwrap(),
+        [
+            AuthQuestion::Phone,
+            AuthQuestion::Code(CodeDelivery::App),
+            AuthQuestion::Password { hint: None },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn a_wrong_code_fails_sign_in() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.transport().require_login("12345", None);
+    let (prompter, mut questions) = ChannelPrompter::new();
+    tokio::spawn(async move {
+        while let Some(request) = questions.next().await {
+            request.answer("00000");
+        }
+    });
+
+    let client = GrokClient::builder(bot.config())
+        .transport(bot.transport())
+        .prompter(prompter)
+        .build()
+        .await;
+
+    assert!(matches!(client, Err(GrokError::Auth(_))));
+    assert!(!bot.transport().is_logged_in());
+}
+
+#[tokio::test]
+async fn an_unanswered_question_fails_sign_in() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.transport().require_login("12345", None);
+    let (prompter, questions) = ChannelPrompter::new();
+    drop(questions);
+
+    let client = GrokClient::builder(bot.config())
+        .transport(bot.transport())
+        .prompter(prompter)
+        .build()
+        .await;
+
+    assert!(matches!(client, Err(GrokError::Auth(_))));
+}
+
+#[tokio::test]
+async fn an_env_prompter_waits_for_the_code_file() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.transport().require_login("12345", None);
+    let code_file = std::env::temp_dir().join(format!("grok-client-code-{}", std::process::id()));
+    let _ = std::fs::remove_file(&code_file);
+
+    let prompter = EnvPrompter::new().phone("+1234567890").code_file(&code_file);
+    let writer = {
+        let code_file = code_file.clone();
+        async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            std::fs::write(&code_file, "12345\n").unwrap();
+        }
+    };
+    let (client, _) = tokio::join!(
+        GrokClient::builder(bot.config())
+            .transport(bot.transport())
+            .prompter(prompter)
+            .build(),
+        writer
+    );
+    std::fs::remove_file(&code_file).unwrap();
+
+    assert!(client.is_ok());
+    assert!(bot.transport().is_logged_in());
+}
+
+#[tokio::test(start_paused = true)]
+async fn an_env_prompter_gives_up_on_a_missing_file() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.transport().require_login("12345", None);
+    let prompter = EnvPrompter::new()
+        .phone("+1234567890")
+        .code_file("/nonexistent/code")
+        .file_timeout(Duration::from_secs(5));
+
+    let client = GrokClient::builder(bot.config())
+        .transport(bot.transport())
+        .prompter(prompter)
+        .build()
+        .await;
+
+    assert!(matches!(client, Err(GrokError::Auth(_))));
+}
+
+#[tokio::test(start_paused = true)]
+async fn qr_login_refreshes_the_code_until_it_is_scanned() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let transport = bot.transport();
+    transport.require_login("unused", None);
+    let (prompter, mut questions) = ChannelPrompter::new();
+
+    let phone = transport.clone();
+    let ui = tokio::spawn(async move {
+        let mut shown = Vec::new();
+        while let Some(request) = questions.next().await {
+            let AuthQuestion::Qr(qr) = &request.question else {
+                panic!("unexpected question {:?}", request.question);
+            };
+            assert!(qr.url().starts_with("tg://login?token="), "{}", qr.url());
+            assert!(qr.to_png().starts_with(b"\x89PNG\r\n\x1a\n"));
+            assert!(!qr.to_terminal().is_empty());
+            shown.push(qr.url());
+            if shown.len() == 2 {
+                phone.scan_qr();
+            }
+        }
+        shown
+    });
+
+    let client = GrokClient::builder(bot.config())
+        .transport(bot.transport())
+        .prompter(prompter)
+        .login_method(LoginMethod::Qr)
+        .build()
+        .await;
+    assert!(client.is_ok());
+    assert!(transport.is_logged_in());
+    assert_eq!(transport.qr_tokens(), 2);
+
+    drop(client);
+    let shown = ui.await.unwrap();
+    assert_eq!(shown.len(), 2);
+    assert_ne!(shown[0], shown[1]);
+}
+
+#[tokio::test]
+async fn qr_login_asks_for_the_2fa_password() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let transport = bot.transport();
+    transport.require_login("unused", Some("hunter2"));
+    let (prompter, mut questions) = ChannelPrompter::new();
+
+    let phone = transport.clone();
+    let ui = tokio::spawn(async move {
+        let mut asked = Vec::new();
+        while let Some(request) = questions.next().await {
+            asked.push(request.question.clone());
+            match request.question {
+                AuthQuestion::Qr(_) => phone.scan_qr(),
+                AuthQuestion::Password { .. } => request.answer("hunter2"),
+                _ => panic!("unexpected question {:?}", request.question),
+            }
+        }
+        asked
+    });
+
+    let client = GrokClient::builder(bot.config())
+        .transport(bot.transport())
+        .prompter(prompter)
+        .login_method(LoginMethod::Qr)
+        .build()
+        .await;
+    assert!(client.is_ok());
+    assert!(transport.is_logged_in());
+    assert!(transport.session_saves() >= 1);
+
+    drop(client);
+    let asked = ui.await.unwrap();
+    assert!(matches!(asked[..], [AuthQuestion::Qr(_), AuthQuestion::Password { hint: None }]));
+}
+
+#[tokio::test]
+async fn qr_login_needs_a_prompter_that_shows_qr_codes() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.transport().require_login("unused", None);
+
+    let client = GrokClient::builder(bot.config())
+        .transport(bot.transport())
+        .prompter(EnvPrompter::new())
+        .login_method(LoginMethod::Qr)
+        .build()
+        .await;
+    assert!(matches!(client, Err(GrokError::Auth(e)) if e.contains("QR")));
+}
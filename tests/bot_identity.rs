@@ -0,0 +1,70 @@
+use grok_client::prelude::*;
+use grok_client::testing::FakeBot;
+use grok_client::transport::{MemoryTransport, PeerKind};
+use grok_client::{MemorySessionStore, SessionStore};
+
+mod common;
+use common::BOT_ID;
+
+async fn connect_with(config: GrokConfig, transport: MemoryTransport) -> Result<GrokClient, GrokError> {
+    GrokClient::with_transport(config, transport).await
+}
+
+#[tokio::test]
+async fn a_pinned_bot_id_must_match() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let mut config = bot.config();
+    config.bot_id = Some(BOT_ID);
+    assert!(connect_with(config.clone(), bot.transport()).await.is_ok());
+
+    config.bot_id = Some(BOT_ID + 1);
+    match connect_with(config, bot.transport()).await {
+        Err(GrokError::BotIdentityMismatch { username, expected, actual }) => {
+            assert_eq!(username, "GrokAI");
+            assert_eq!(expected, BOT_ID + 1);
+            assert_eq!(actual, BOT_ID);
+        }
+        other => panic!("expected BotIdentityMismatch, got {:?}", other.err()),
+    }
+    assert!(bot.transport().sent().is_empty());
+}
+
+#[tokio::test]
+async fn a_username_that_is_not_a_bot_is_refused() {
+    let transport = MemoryTransport::new();
+    transport.add_user("GrokAI", BOT_ID, PeerKind::User);
+    let config = GrokConfig::new(1, "test", "GrokAI", "fake-bot.session");
+
+    let result = connect_with(config, transport).await;
+    assert!(matches!(result, Err(GrokError::Bot(_))), "{:?}", result.err());
+}
+
+#[tokio::test]
+async fn trust_on_first_use_remembers_the_bot() {
+    let store = MemorySessionStore::new();
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let mut config = bot.config();
+    config.trust_bot_on_first_use = true;
+
+    let first = GrokClient::builder(config.clone())
+        .transport(bot.transport())
+        .session_store(store.clone())
+        .build()
+        .await;
+    assert!(first.is_ok());
+    assert_eq!(store.trusted_bot("grokai").unwrap(), Some(BOT_ID));
+
+    // The username now points at someone else
+    let hijacked = MemoryTransport::new();
+    hijacked.add_user("GrokAI", 666, PeerKind::Bot);
+    let second = GrokClient::builder(config)
+        .transport(hijacked.clone())
+        .session_store(store)
+        .build()
+        .await;
+    assert!(matches!(
+        second.err(),
+        Some(GrokError::BotIdentityMismatch { expected: BOT_ID, actual: 666, .. })
+    ));
+    assert!(hijacked.sent().is_empty());
+}
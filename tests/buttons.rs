@@ -0,0 +1,120 @@
+use grok_client::prelude::*;
+use grok_client::testing::{FakeBot, Rule};
+use grok_client::{Button, ButtonKind, ButtonResponse};
+
+mod common;
+use common::{connect, BOT_ID};
+
+fn menu() -> Vec<Vec<Button>> {
+    vec![
+        vec![
+            Button::callback("Regenerate", "regen"),
+            Button::callback("Continue", "more"),
+        ],
+        vec![
+            Button::callback("Copy", "copy"),
+            Button::url("Open in browser", "https://grok.example/c/1"),
+        ],
+    ]
+}
+
+fn bot() -> FakeBot {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::any().reply_with_buttons("Answer v1", menu()));
+    bot.add_rule(Rule::pressed("regen").edit("Answer v2"));
+    bot.add_rule(Rule::pressed("more").reply("Part 2"));
+    bot.add_rule(Rule::pressed("copy").answer("Copied"));
+    bot
+}
+
+#[tokio::test]
+async fn replies_expose_their_buttons() {
+    let bot = bot();
+    let client = connect(&bot).await;
+
+    let reply = client.ask("hi", RequestPriority::Normal).await.unwrap();
+    assert_eq!(reply.buttons, menu());
+    assert_eq!(reply.button("Continue").unwrap().data(), Some(&b"more"[..]));
+    assert_eq!(
+        reply.button("Open in browser").unwrap().kind,
+        ButtonKind::Url("https://grok.example/c/1".into())
+    );
+    assert!(reply.button("Nope").is_none());
+}
+
+#[tokio::test]
+async fn pressing_returns_the_edited_message() {
+    let bot = bot();
+    let client = connect(&bot).await;
+
+    let reply = client.ask("hi", RequestPriority::Normal).await.unwrap();
+    let regenerate = reply.button("Regenerate").unwrap();
+    let ButtonResponse::Reply(edited) = client.press(&reply, regenerate).await.unwrap() else {
+        panic!("expected the edited message");
+    };
+    assert_eq!(edited.message_id, reply.message_id);
+    assert_eq!(edited.text, "Answer v2");
+    assert!(edited.edited);
+    // Still there to press again
+    assert_eq!(edited.buttons, menu());
+}
+
+#[tokio::test]
+async fn pressing_returns_a_new_message_or_a_notification() {
+    let bot = bot();
+    let client = connect(&bot).await;
+    let reply = client.ask("hi", RequestPriority::Normal).await.unwrap();
+
+    let more = client
+        .press(&reply, reply.button("Continue").unwrap())
+        .await
+        .unwrap();
+    let ButtonResponse::Reply(next) = more else {
+        panic!("expected a new message");
+    };
+    assert_eq!(next.text, "Part 2");
+    assert!(!next.edited);
+    assert_eq!(next.reply_to, Some(reply.message_id));
+
+    let copy = client
+        .press(&reply, reply.button("Copy").unwrap())
+        .await
+        .unwrap();
+    let ButtonResponse::Answer(answer) = copy else {
+        panic!("expected a notification");
+    };
+    assert_eq!(answer.message.as_deref(), Some("Copied"));
+    assert!(!answer.alert);
+}
+
+#[tokio::test]
+async fn url_and_unknown_buttons_cannot_be_pressed() {
+    let bot = bot();
+    let client = connect(&bot).await;
+    let reply = client.ask("hi", RequestPriority::Normal).await.unwrap();
+
+    let url = reply.button("Open in browser").unwrap();
+    assert!(matches!(
+        client.press(&reply, url).await,
+        Err(GrokError::Bot(_))
+    ));
+
+    let forged = Button::callback("Admin", "admin");
+    assert!(matches!(
+        client.press(&reply, &forged).await,
+        Err(GrokError::Invocation(e)) if e == "DATA_INVALID"
+    ));
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_bot_that_never_reacts_times_out() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(
+        Rule::any().reply_with_buttons("Pick one", vec![vec![Button::callback("A", "a")]]),
+    );
+    let client = connect(&bot).await;
+    let reply = client.ask("hi", RequestPriority::Normal).await.unwrap();
+
+    let result = client.press(&reply, &reply.buttons[0][0]).await;
+    assert!(matches!(result, Err(GrokError::Timeout(_))));
+}
@@ -0,0 +1,21 @@
+#![allow(dead_code)]
+
+use grok_client::testing::FakeBot;
+use grok_client::GrokClient;
+use std::time::Duration;
+
+pub const BOT_ID: i64 = 42;
+
+pub fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+/// Connects a started client to `bot` with short timeouts.
+pub async fn connect(bot: &FakeBot) -> GrokClient {
+    let mut config = bot.config();
+    config.response_timeout = 5;
+    config.stream_quiet_period_ms = 500;
+    let client = GrokClient::with_transport(config, bot.transport()).await.unwrap();
+    client.start();
+    client
+}
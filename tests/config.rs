@@ -0,0 +1,402 @@
+use grok_client::testing::FakeBot;
+use grok_client::{ConfigError, GrokClient, GrokConfig, GrokError, RateLimit, RequestPriority};
+use std::path::Path;
+use std::time::Duration;
+
+const REQUIRED: [(&str, &str); 3] = [
+    ("API_ID", "12345"),
+    ("API_HASH", "abcdef"),
+    ("BOT_USERNAME", "GrokAI"),
+];
+
+fn with(extra: &[(&str, &str)]) -> Result<GrokConfig, ConfigError> {
+    GrokConfig::from_vars(REQUIRED.iter().chain(extra).copied())
+}
+
+#[test]
+fn reads_the_dotenv_keys() {
+    let config = with(&[
+        ("SESSION_PATH", "work.session"),
+        ("RESPONSE_TIMEOUT", "45"),
+        ("QUEUE_INTERVAL", "250"),
+    ])
+    .unwrap();
+
+    assert_eq!(config.api_id, 12345);
+    assert_eq!(config.api_hash, "abcdef");
+    assert_eq!(config.bot_username, "GrokAI");
+    assert_eq!(config.session_path, Path::new("work.session"));
+    assert_eq!(config.response_timeout, 45);
+    assert_eq!(config.rate_limit, RateLimit::every(Duration::from_millis(250)));
+}
+
+#[test]
+fn optional_keys_fall_back_to_defaults() {
+    let config = with(&[]).unwrap();
+    let defaults = GrokConfig::new(12345, "abcdef", "GrokAI", "session.session");
+
+    assert_eq!(config.session_path, defaults.session_path);
+    assert_eq!(config.response_timeout, defaults.response_timeout);
+    assert_eq!(config.rate_limit, defaults.rate_limit);
+    assert_eq!(config.stream_final_marker, None);
+}
+
+#[test]
+fn missing_and_malformed_values_are_reported_by_key() {
+    let missing = GrokConfig::from_vars([("API_ID", "1"), ("BOT_USERNAME", "GrokAI")]);
+    assert!(matches!(missing, Err(ConfigError::Missing { key: "API_HASH" })));
+
+    let empty = GrokConfig::from_vars([("API_ID", "1"), ("API_HASH", " "), ("BOT_USERNAME", "b")]);
+    assert!(matches!(empty, Err(ConfigError::Missing { key: "API_HASH" })));
+
+    match with(&[("RESPONSE_TIMEOUT", "soon")]) {
+        Err(ConfigError::Invalid { key, value, .. }) => {
+            assert_eq!(key, "RESPONSE_TIMEOUT");
+            assert_eq!(value, "soon");
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+
+    let zero = with(&[("QUEUE_INTERVAL", "0")]);
+    assert!(matches!(zero, Err(ConfigError::Invalid { key, .. }) if key == "QUEUE_INTERVAL"));
+}
+
+#[test]
+fn grok_prefix_overrides_and_sets_every_tunable() {
+    let config = with(&[
+        ("RESPONSE_TIMEOUT", "45"),
+        ("GROK_RESPONSE_TIMEOUT", "10"),
+        ("GROK_STREAM_QUIET_PERIOD_MS", "750"),
+        ("GROK_STREAM_FINAL_MARKER", "[done]"),
+        ("GROK_RECONNECT_INITIAL_DELAY_MS", "100"),
+        ("GROK_RECONNECT_MAX_DELAY_MS", "5000"),
+        ("GROK_RATE_LIMIT_PER_SECOND", "20"),
+        ("GROK_RATE_LIMIT_BURST", "5"),
+        ("GROK_RATE_LIMIT_LOW_PER_SECOND", "0.5"),
+        ("GROK_RATE_LIMIT_LOW_BURST", "2"),
+        ("GROK_BOT_ID", "42"),
+        ("GROK_TRUST_BOT_ON_FIRST_USE", "true"),
+    ])
+    .unwrap();
+
+    assert_eq!(config.response_timeout, 10);
+    assert_eq!(config.stream_quiet_period_ms, 750);
+    assert_eq!(config.stream_final_marker.as_deref(), Some("[done]"));
+    assert_eq!(config.reconnect_initial_delay_ms, 100);
+    assert_eq!(config.reconnect_max_delay_ms, 5000);
+    assert_eq!(config.rate_limit, RateLimit::new(20.0, 5));
+    assert_eq!(
+        config.priority_rate_limits.get(&RequestPriority::Low),
+        Some(&RateLimit::new(0.5, 2))
+    );
+    assert_eq!(config.priority_rate_limits.len(), 1);
+    assert_eq!(config.bot_id, Some(42));
+    assert!(config.trust_bot_on_first_use);
+}
+
+#[test]
+fn a_priority_burst_needs_a_rate() {
+    let config = with(&[("GROK_RATE_LIMIT_HIGH_BURST", "3")]);
+    assert!(matches!(
+        config,
+        Err(ConfigError::Invalid { key, .. }) if key == "GROK_RATE_LIMIT_HIGH_BURST"
+    ));
+}
+
+// Tests that change the process environment take turns.
+static ENV: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+#[test]
+fn from_dotenv_reads_the_file() {
+    let _env = ENV.lock().unwrap_or_else(|e| e.into_inner());
+    let path = std::env::temp_dir().join(format!("grok-client-{}.env", std::process::id()));
+    std::fs::write(
+        &path,
+        "API_ID=777\nAPI_HASH=fromfile\nBOT_USERNAME=FileBot\nQUEUE_INTERVAL=500\n",
+    )
+    .unwrap();
+
+    let config = GrokConfig::from_dotenv(&path);
+    std::fs::remove_file(&path).unwrap();
+    let config = config.unwrap();
+
+    assert_eq!(config.api_id, 777);
+    assert_eq!(config.bot_username, "FileBot");
+    assert_eq!(config.rate_limit, RateLimit::every(Duration::from_millis(500)));
+
+    let missing = GrokConfig::from_dotenv(Path::new("/nonexistent/.env"));
+    assert!(matches!(missing, Err(ConfigError::File { .. })));
+}
+
+#[cfg(unix)]
+#[test]
+fn variables_that_are_not_utf8_fail_only_if_they_are_ours() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let _env = ENV.lock().unwrap_or_else(|e| e.into_inner());
+    let junk = OsStr::from_bytes(b"\xff\xfe");
+    std::env::set_var("API_ID", "1");
+    std::env::set_var("API_HASH", "hash");
+    std::env::set_var("BOT_USERNAME", "GrokAI");
+    std::env::set_var("GROK_CONFIG_TEST_JUNK", junk);
+    assert_eq!(GrokConfig::from_env().unwrap().api_id, 1);
+
+    std::env::set_var("GROK_STREAM_FINAL_MARKER", junk);
+    let config = GrokConfig::from_env();
+    for key in [
+        "API_ID",
+        "API_HASH",
+        "BOT_USERNAME",
+        "GROK_CONFIG_TEST_JUNK",
+        "GROK_STREAM_FINAL_MARKER",
+    ] {
+        std::env::remove_var(key);
+    }
+    assert!(matches!(
+        config,
+        Err(ConfigError::Invalid { key, .. }) if key == "GROK_STREAM_FINAL_MARKER"
+    ));
+}
+
+/// Writes `contents` to a fresh temp file named `name`, removed on drop.
+struct TempFile(std::path::PathBuf);
+
+impl TempFile {
+    fn new(name: &str, contents: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("grok-client-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        Self(path)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+const BASE_TOML: &str = r#"
+api_id = 12345
+api_hash = "abcdef"
+bot_username = "GrokAI"
+response_timeout = 20
+
+[rate_limit]
+per_second = 2.0
+burst = 4
+
+[profile.work]
+session_path = "work.session"
+api_id = 999
+
+[profile.test]
+bot_username = "GrokTestBot"
+"#;
+
+#[test]
+fn a_profile_is_layered_over_the_file() {
+    let file = TempFile::new("profiles.toml", BASE_TOML);
+
+    let base = GrokConfig::loader().file(&file.0).load().unwrap();
+    assert_eq!(base.api_id, 12345);
+    assert_eq!(base.session_path, Path::new("session.session"));
+    assert_eq!(base.response_timeout, 20);
+    assert_eq!(base.rate_limit, RateLimit::new(2.0, 4));
+
+    let work = GrokConfig::loader()
+        .file(&file.0)
+        .profile("work")
+        .load()
+        .unwrap();
+    assert_eq!(work.api_id, 999);
+    assert_eq!(work.session_path, Path::new("work.session"));
+    assert_eq!(work.bot_username, "GrokAI");
+
+    let missing = GrokConfig::loader().file(&file.0).profile("home").load();
+    assert!(matches!(missing, Err(ConfigError::UnknownProfile(name)) if name == "home"));
+}
+
+#[test]
+fn later_layers_win() {
+    let base = TempFile::new("layers.toml", BASE_TOML);
+    let local = TempFile::new(
+        "layers.local.json",
+        r#"{ "rate_limit": { "burst": 8 }, "response_timeout": 25 }"#,
+    );
+
+    let config = GrokConfig::loader()
+        .file(&base.0)
+        .file(&local.0)
+        .profile("test")
+        .vars([("GROK_RESPONSE_TIMEOUT", "40"), ("API_HASH", "fromenv")])
+        .set(|c| c.api_hash = "explicit".into())
+        .load()
+        .unwrap();
+
+    assert_eq!(config.bot_username, "GrokTestBot");
+    assert_eq!(config.rate_limit, RateLimit::new(2.0, 8));
+    assert_eq!(config.response_timeout, 40);
+    assert_eq!(config.api_hash, "explicit");
+}
+
+#[test]
+fn grok_profile_selects_a_profile_and_env_can_complete_a_priority_limit() {
+    let file = TempFile::new(
+        "env-profile.toml",
+        &format!("{BASE_TOML}\n[priority_rate_limits.Low]\nper_second = 0.5\n"),
+    );
+
+    let config = GrokConfig::loader()
+        .file(&file.0)
+        .vars([("GROK_PROFILE", "work"), ("GROK_RATE_LIMIT_LOW_BURST", "3")])
+        .load()
+        .unwrap();
+
+    assert_eq!(config.session_path, Path::new("work.session"));
+    assert_eq!(
+        config.priority_rate_limits.get(&RequestPriority::Low),
+        Some(&RateLimit::new(0.5, 3))
+    );
+}
+
+#[test]
+fn file_problems_are_reported() {
+    let optional = GrokConfig::loader()
+        .optional_file("/nonexistent/grok.toml")
+        .vars(REQUIRED)
+        .load();
+    assert!(optional.is_ok());
+
+    let required = GrokConfig::loader().file("/nonexistent/grok.toml").load();
+    assert!(matches!(required, Err(ConfigError::File { .. })));
+
+    let broken = TempFile::new("broken.toml", "api_id = ");
+    assert!(matches!(
+        GrokConfig::loader().file(&broken.0).load(),
+        Err(ConfigError::File { .. })
+    ));
+
+    let incomplete = TempFile::new("incomplete.toml", "api_id = 1\nbot_username = \"b\"\n");
+    assert!(matches!(
+        GrokConfig::loader().file(&incomplete.0).load(),
+        Err(ConfigError::Missing { key: "api_hash" })
+    ));
+
+    let mistyped = TempFile::new(
+        "mistyped.json",
+        r#"{ "api_id": "one", "api_hash": "h", "bot_username": "b" }"#,
+    );
+    assert!(matches!(
+        GrokConfig::loader().file(&mistyped.0).load(),
+        Err(ConfigError::Malformed(_))
+    ));
+}
+
+fn problems(mut config: GrokConfig) -> Vec<String> {
+    match config.validate() {
+        Ok(()) => Vec::new(),
+        Err(ConfigError::Validation(problems)) => problems.into_iter().map(|p| p.field).collect(),
+        Err(other) => panic!("unexpected {other:?}"),
+    }
+}
+
+#[test]
+fn validate_normalises_the_bot_username() {
+    for username in ["@GrokAI", "t.me/GrokAI", "https://t.me/GrokAI/", " @GrokAI "] {
+        let mut config = GrokConfig::new(12345, "abcdef", username, "session.session");
+        config.validate().unwrap();
+        assert_eq!(config.bot_username, "GrokAI");
+    }
+}
+
+#[test]
+fn validate_reports_every_problem_at_once() {
+    let mut config = GrokConfig::new(0, " ", "@no", "/nonexistent/dir/session.session");
+    config.response_timeout = 0;
+    config.rate_limit = RateLimit::new(0.0, 0);
+
+    assert_eq!(
+        problems(config),
+        [
+            "api_id",
+            "api_hash",
+            "bot_username",
+            "session_path",
+            "response_timeout",
+            "rate_limit.per_second",
+            "rate_limit.burst",
+        ]
+    );
+}
+
+#[test]
+fn validate_problems_name_a_fix() {
+    let mut config = GrokConfig::new(12345, "*****", "GrokAI", "session.session");
+    config.reconnect_max_delay_ms = 10;
+    config
+        .priority_rate_limits
+        .insert(RequestPriority::Low, RateLimit::new(f64::NAN, 1));
+
+    let Err(ConfigError::Validation(found)) = config.validate() else {
+        panic!("expected validation errors");
+    };
+    let fields: Vec<_> = found.iter().map(|p| p.field.as_str()).collect();
+    assert_eq!(
+        fields,
+        ["api_hash", "reconnect_max_delay_ms", "priority_rate_limits.Low.per_second"]
+    );
+    assert!(found.iter().all(|p| !p.fix.is_empty()));
+
+    let message = ConfigError::Validation(found).to_string();
+    assert!(message.contains("api_hash: contains characters"), "{message}");
+}
+
+#[test]
+fn validate_checks_the_session_path() {
+    let dir = std::env::temp_dir();
+    assert_eq!(
+        problems(GrokConfig::new(1, "h", "GrokAI", &dir)),
+        ["session_path"]
+    );
+    assert!(problems(GrokConfig::new(1, "h", "GrokAI", dir.join("fresh.session"))).is_empty());
+    assert!(problems(GrokConfig::new(1, "h", "GrokAI", "relative.session")).is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn validate_checks_that_the_session_directory_is_writable() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join(format!("grok-client-readonly-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // Writable by others but not by us, the owner
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o577)).unwrap();
+    // Root may write anywhere, so there is nothing to check
+    let writable = std::fs::File::create(dir.join("probe")).is_ok();
+
+    let found = problems(GrokConfig::new(1, "h", "GrokAI", dir.join("grok.session")));
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    if !writable {
+        assert_eq!(found, ["session_path"]);
+    }
+}
+
+#[tokio::test]
+async fn the_client_refuses_an_invalid_config() {
+    let bot = FakeBot::new("GrokAI", 42);
+    let mut config = bot.config();
+    config.bot_username = "@GrokAI".into();
+    assert!(GrokClient::with_transport(config, bot.transport()).await.is_ok());
+
+    let mut config = bot.config();
+    config.response_timeout = 0;
+    let result = GrokClient::with_transport(config, bot.transport()).await;
+    assert!(matches!(
+        result,
+        Err(GrokError::Config(ConfigError::Validation(_)))
+    ));
+}
@@ -0,0 +1,113 @@
+use grok_client::prelude::*;
+use grok_client::testing::{FakeBot, Rule};
+use grok_client::{Backoff, ConnectionState};
+use std::time::Duration;
+
+mod common;
+use common::{connect, ms, BOT_ID};
+
+#[tokio::test(start_paused = true)]
+async fn reconnects_after_the_connection_drops() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::matching("ping").reply("pong"));
+    let client = connect(&bot).await;
+    let mut state = client.connection_state();
+    assert_eq!(*state.borrow(), ConnectionState::Connected);
+
+    bot.transport().fail_connects(2);
+    bot.transport().disconnect();
+
+    state.changed().await.unwrap();
+    assert_eq!(*state.borrow(), ConnectionState::Reconnecting { attempt: 1 });
+    state.wait_for(|s| *s == ConnectionState::Connected).await.unwrap();
+    assert_eq!(bot.transport().connect_count(), 4);
+
+    let reply = client.ask("ping", RequestPriority::Normal).await.unwrap();
+    assert_eq!(reply.text, "pong");
+}
+
+#[tokio::test(start_paused = true)]
+async fn messages_queued_while_disconnected_are_sent_after_reconnecting() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::any().reply("ok"));
+    let client = connect(&bot).await;
+    let mut state = client.connection_state();
+
+    bot.transport().fail_connects(1);
+    bot.transport().disconnect();
+    state.wait_for(|s| *s != ConnectionState::Connected).await.unwrap();
+
+    client.send("first", RequestPriority::Low).await.unwrap();
+    let reply = client.ask("second", RequestPriority::High).await.unwrap();
+
+    assert_eq!(reply.text, "ok");
+    tokio::time::sleep(ms(500)).await;
+    assert_eq!(bot.prompts(), ["second", "first"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn updates_received_while_disconnected_are_delivered_after_reconnecting() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::any().wait(Duration::from_secs(1)).reply("late answer"));
+    let client = connect(&bot).await;
+    let mut state = client.connection_state();
+
+    let ask = client.ask("question", RequestPriority::Normal);
+    let drop_connection = async {
+        tokio::time::sleep(ms(500)).await;
+        bot.transport().disconnect();
+        state.wait_for(|s| *s == ConnectionState::Connected).await.unwrap();
+    };
+    let (reply, _) = tokio::join!(ask, drop_connection);
+
+    assert_eq!(reply.unwrap().text, "late answer");
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_revoked_session_requires_signing_in_again() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = connect(&bot).await;
+    let mut state = client.connection_state();
+
+    bot.transport().require_login("12345", None);
+    bot.transport().disconnect();
+
+    state.wait_for(|s| *s == ConnectionState::AuthRequired).await.unwrap();
+    let connects = bot.transport().connect_count();
+    tokio::time::sleep(Duration::from_secs(120)).await;
+    assert_eq!(bot.transport().connect_count(), connects);
+}
+
+#[tokio::test(start_paused = true)]
+async fn shutdown_gives_up_on_the_queue_while_signed_out() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = GrokClient::with_transport(bot.config(), bot.transport())
+        .await
+        .unwrap();
+    let handle = client.start();
+    let mut state = client.connection_state();
+
+    bot.transport().require_login("12345", None);
+    bot.transport().disconnect();
+    state.wait_for(|s| *s == ConnectionState::AuthRequired).await.unwrap();
+
+    client.send("stuck", RequestPriority::Normal).await.unwrap();
+    let report = tokio::time::timeout(Duration::from_secs(5), handle.shutdown(ms(1000)))
+        .await
+        .expect("shutdown should give up at its deadline");
+    assert_eq!(report.unsent.len(), 1);
+    assert!(bot.prompts().is_empty());
+}
+
+#[test]
+fn backoff_grows_with_jitter_up_to_the_maximum() {
+    let mut backoff = Backoff::new(ms(100), ms(1000));
+    let steps = [100, 200, 400, 800, 1000, 1000];
+    for step in steps {
+        let delay = backoff.next_delay();
+        assert!(delay >= ms(step / 2) && delay <= ms(step), "{delay:?} for step {step}");
+    }
+
+    backoff.reset();
+    assert!(backoff.next_delay() <= ms(100));
+}
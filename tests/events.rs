@@ -0,0 +1,113 @@
+use grok_client::media::Upload;
+use grok_client::prelude::*;
+use grok_client::testing::{FakeBot, Rule};
+use grok_client::{ClientEvent, ConnectionState};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+mod common;
+use common::{connect, ms, BOT_ID};
+
+/// Every event received so far, without waiting.
+fn drain(events: &mut broadcast::Receiver<ClientEvent>) -> Vec<ClientEvent> {
+    std::iter::from_fn(|| events.try_recv().ok()).collect()
+}
+
+#[tokio::test]
+async fn a_request_is_queued_sent_and_answered() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::any().reply("v1").wait(ms(50)).edit("v2"));
+    let client = connect(&bot).await;
+    let mut events = client.subscribe();
+
+    let reply = client.ask("hello", RequestPriority::High).await.unwrap();
+    tokio::time::sleep(ms(200)).await;
+
+    let events = drain(&mut events);
+    let ClientEvent::Queued { id, priority, text } = &events[0] else {
+        panic!("expected Queued, got {:?}", events[0]);
+    };
+    assert_eq!((*priority, text.as_str()), (RequestPriority::High, "hello"));
+    let ClientEvent::Sent {
+        id: sent_id,
+        message_id,
+        text,
+        ..
+    } = &events[1]
+    else {
+        panic!("expected Sent, got {:?}", events[1]);
+    };
+    assert_eq!((sent_id, text.as_str()), (id, "hello"));
+    let ClientEvent::ReplyReceived(received) = &events[2] else {
+        panic!("expected ReplyReceived, got {:?}", events[2]);
+    };
+    assert_eq!(received.message_id, reply.message_id);
+    assert_eq!(received.request_message_id, Some(*message_id));
+    let ClientEvent::ReplyEdited(edited) = &events[3] else {
+        panic!("expected ReplyEdited, got {:?}", events[3]);
+    };
+    assert_eq!(edited.text, "v2");
+    assert_eq!(edited.request_message_id, Some(*message_id));
+    assert_eq!(events.len(), 4);
+}
+
+#[tokio::test]
+async fn failed_sends_are_reported() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = connect(&bot).await;
+    let mut events = client.subscribe();
+
+    let missing = std::env::temp_dir().join("grok-client-events-missing.png");
+    client
+        .send(Upload::photo(missing), RequestPriority::Normal)
+        .await
+        .unwrap();
+    tokio::time::sleep(ms(200)).await;
+
+    let events = drain(&mut events);
+    assert_eq!(events.len(), 2, "{:?}", events);
+    assert!(matches!(&events[1], ClientEvent::SendFailed { .. }));
+}
+
+#[tokio::test(start_paused = true)]
+async fn flood_waits_and_reconnects_are_reported() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = connect(&bot).await;
+    let mut events = client.subscribe();
+
+    bot.transport().flood_wait(Duration::from_secs(3));
+    client.send("slow", RequestPriority::Normal).await.unwrap();
+    tokio::time::sleep(Duration::from_secs(4)).await;
+
+    bot.transport().fail_connects(1);
+    bot.transport().disconnect();
+    let mut state = client.connection_state();
+    state
+        .wait_for(|s| *s == ConnectionState::Reconnecting { attempt: 2 })
+        .await
+        .unwrap();
+    state
+        .wait_for(|s| *s == ConnectionState::Connected)
+        .await
+        .unwrap();
+
+    let events = drain(&mut events);
+    assert!(events
+        .iter()
+        .any(|e| matches!(e, ClientEvent::FloodWait { wait } if *wait == Duration::from_secs(3))));
+    let states: Vec<_> = events
+        .iter()
+        .filter_map(|e| match e {
+            ClientEvent::ConnectionStateChanged(state) => Some(*state),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        states,
+        [
+            ConnectionState::Reconnecting { attempt: 1 },
+            ConnectionState::Reconnecting { attempt: 2 },
+            ConnectionState::Connected,
+        ]
+    );
+}
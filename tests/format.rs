@@ -0,0 +1,110 @@
+use grok_client::format::{self, Entity, EntityKind};
+use grok_client::prelude::*;
+use grok_client::testing::{FakeBot, Rule};
+use grok_client::transport::OutgoingMessage;
+
+mod common;
+use common::{connect, BOT_ID};
+
+#[tokio::test]
+async fn markdown_prompts_go_out_as_text_and_entities() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::any().reply("ok"));
+    let client = connect(&bot).await;
+
+    let prompt = OutgoingMessage::markdown("Explain **this** [page](https://example.com)");
+    client.ask(prompt, RequestPriority::Normal).await.unwrap();
+
+    let sent = &bot.received()[0].message;
+    assert_eq!(sent.text, "Explain this page");
+    assert_eq!(
+        sent.entities,
+        vec![
+            Entity::new(EntityKind::Bold, 8, 4),
+            Entity::new(
+                EntityKind::TextUrl {
+                    url: "https://example.com".into()
+                },
+                13,
+                4
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn html_prompts_are_parsed_too() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = connect(&bot).await;
+
+    client
+        .send(
+            OutgoingMessage::html("<i>why</i> <code>x</code>?"),
+            RequestPriority::Normal,
+        )
+        .await
+        .unwrap();
+    tokio::time::sleep(common::ms(300)).await;
+
+    let sent = &bot.received()[0].message;
+    assert_eq!(sent.text, "why x?");
+    let kinds: Vec<_> = sent.entities.iter().map(|e| e.kind.clone()).collect();
+    assert_eq!(kinds, vec![EntityKind::Italic, EntityKind::Code]);
+}
+
+#[tokio::test]
+async fn replies_keep_code_blocks_and_links() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(
+        Rule::any().reply_markdown(
+            "Use **this**:\n```rust\nfn main() {}\n```\nSee [docs](https://docs.rs)",
+        ),
+    );
+    let client = connect(&bot).await;
+
+    let reply = client
+        .ask("code please", RequestPriority::Normal)
+        .await
+        .unwrap();
+    assert!(reply.text.starts_with("Use this:"), "{}", reply.text);
+    assert!(!reply.text.contains("```"), "{}", reply.text);
+
+    let pre = reply
+        .entities
+        .iter()
+        .find(|e| matches!(e.kind, EntityKind::Pre { .. }))
+        .unwrap();
+    assert_eq!(
+        pre.kind,
+        EntityKind::Pre {
+            language: Some("rust".into())
+        }
+    );
+    assert_eq!(pre.text(&reply.text).trim_end(), "fn main() {}");
+
+    let markdown = reply.to_markdown();
+    assert!(markdown.contains("**this**"), "{}", markdown);
+    assert!(
+        markdown.contains("```rust\nfn main() {}\n```"),
+        "{}",
+        markdown
+    );
+    assert!(markdown.contains("[docs](https://docs.rs)"), "{}", markdown);
+
+    let html = reply.to_html();
+    assert!(html.contains("<b>this</b>"), "{}", html);
+    assert!(
+        html.contains("<a href=\"https://docs.rs\">docs</a>"),
+        "{}",
+        html
+    );
+}
+
+#[test]
+fn offsets_count_utf16_code_units() {
+    let (text, entities) = format::parse_markdown("🚀 **go**");
+    assert_eq!(text, "🚀 go");
+    assert_eq!(entities, vec![Entity::new(EntityKind::Bold, 3, 2)]);
+    assert_eq!(entities[0].text(&text), "go");
+    assert_eq!(format::to_html(&text, &entities), "🚀 <b>go</b>");
+}
@@ -0,0 +1,161 @@
+use async_trait::async_trait;
+use grok_client::filters::{self, Filter, MessageKind};
+use grok_client::prelude::*;
+use grok_client::testing::{FakeBot, Rule};
+use grok_client::transport::PeerKind;
+use std::sync::{Arc, Mutex};
+
+mod common;
+use common::{connect, ms, BOT_ID};
+
+type Log = Arc<Mutex<Vec<String>>>;
+
+struct Recorder {
+    log: Log,
+    name: &'static str,
+    flow: Propagation,
+}
+
+#[async_trait]
+impl MessageHandler for Recorder {
+    async fn handle(&self, ctx: &MessageContext) -> Propagation {
+        let edit = if ctx.is_edit() { " (edit)" } else { "" };
+        let entry = format!("{}: {}{}", self.name, ctx.text(), edit);
+        self.log.lock().unwrap().push(entry);
+        self.flow
+    }
+}
+
+fn recorder(log: &Log, name: &'static str, flow: Propagation) -> Recorder {
+    Recorder {
+        log: log.clone(),
+        name,
+        flow,
+    }
+}
+
+fn entries(log: &Log) -> Vec<String> {
+    log.lock().unwrap().clone()
+}
+
+#[tokio::test(start_paused = true)]
+async fn handlers_run_in_order_and_can_stop_the_chain() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = connect(&bot).await;
+    let log = Log::default();
+
+    client.add_custom_handler(recorder(&log, "late", Propagation::Continue));
+    client.add_handler_with_order(-1, recorder(&log, "early", Propagation::Continue));
+    client.add_custom_handler(recorder(&log, "stopper", Propagation::Stop));
+    client.add_custom_handler(recorder(&log, "never", Propagation::Continue));
+
+    bot.say("hello");
+    tokio::time::sleep(ms(50)).await;
+
+    assert_eq!(entries(&log), ["early: hello", "late: hello", "stopper: hello"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn removed_handlers_no_longer_receive_messages() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = connect(&bot).await;
+    let log = Log::default();
+
+    let handle = client.add_custom_handler(recorder(&log, "h", Propagation::Continue));
+    bot.say("one");
+    tokio::time::sleep(ms(50)).await;
+
+    assert!(handle.remove());
+    bot.say("two");
+    tokio::time::sleep(ms(50)).await;
+
+    assert_eq!(entries(&log), ["h: one"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn context_carries_the_originating_request_and_edits() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::any().reply("po").edit("pong"));
+    let client = connect(&bot).await;
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let sink = seen.clone();
+    client.add_custom_handler(handler_fn(move |ctx: MessageContext| {
+        let sink = sink.clone();
+        async move {
+            let request = ctx.request().map(|r| r.text.clone());
+            sink.lock().unwrap().push((ctx.text().to_string(), ctx.is_edit(), request));
+            Propagation::Continue
+        }
+    }));
+
+    client.ask("ping", RequestPriority::Normal).await.unwrap();
+    tokio::time::sleep(ms(50)).await;
+
+    let ping = Some("ping".to_string());
+    assert_eq!(
+        *seen.lock().unwrap(),
+        [("po".to_string(), false, ping.clone()), ("pong".to_string(), true, ping)]
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn filters_select_the_messages_a_handler_sees() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = connect(&bot).await;
+    let transport = bot.transport();
+    let friend = transport.add_user("friend", 7, PeerKind::User);
+    let log = Log::default();
+
+    client.add_custom_handler(
+        recorder(&log, "bot-errors", Propagation::Continue)
+            .with_filter(filters::from_bot().and(filters::regex("(?i)error").unwrap())),
+    );
+    client.add_custom_handler(
+        recorder(&log, "friend", Propagation::Continue).with_filter(filters::chat(friend.id)),
+    );
+    client.add_custom_handler(
+        recorder(&log, "text-only", Propagation::Continue)
+            .with_filter(filters::kind(MessageKind::Text).and(filters::edited().not())),
+    );
+
+    bot.say("all good");
+    bot.say("ERROR: overloaded");
+    let from_friend = transport.receive(friend.id, "hi there", None);
+    transport.edit(&from_friend, "hi there!");
+    tokio::time::sleep(ms(50)).await;
+
+    assert_eq!(
+        entries(&log),
+        [
+            "text-only: all good",
+            "bot-errors: ERROR: overloaded",
+            "text-only: ERROR: overloaded",
+            "friend: hi there",
+            "text-only: hi there",
+            "friend: hi there! (edit)",
+        ]
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn handlers_can_reply_through_the_context() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = connect(&bot).await;
+
+    client.add_custom_handler(
+        handler_fn(|ctx: MessageContext| async move {
+            ctx.reply("thanks!").await.unwrap();
+            Propagation::Continue
+        })
+        .with_filter(filters::regex("^news").unwrap()),
+    );
+
+    bot.say("news: it works");
+    tokio::time::sleep(ms(500)).await;
+
+    let sent = bot.received();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].message.text, "thanks!");
+    assert_eq!(sent[0].message.reply_to, Some(sent[0].id - 1));
+}
@@ -1,6 +1,192 @@
-#[tokio::test]
-async fn test_basic_flow() {
-    // Тестирование авторизации
-    // Тестирование очереди
-    // Тестирование обработчиков
+use futures::StreamExt;
+use grok_client::prelude::*;
+use grok_client::testing::{FakeBot, Rule};
+use grok_client::transport::PeerKind;
+use std::time::Duration;
+
+mod common;
+use common::{connect, ms, BOT_ID};
+
+#[tokio::test(start_paused = true)]
+async fn ask_resolves_with_the_reply_to_its_prompt() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::matching("^ping").wait(ms(200)).reply("pong"));
+    let client = connect(&bot).await;
+
+    let reply = client.ask("ping", RequestPriority::Normal).await.unwrap();
+    assert_eq!(reply.text, "pong");
+    assert_eq!(reply.request_message_id, Some(bot.received()[0].id));
+}
+
+#[tokio::test(start_paused = true)]
+async fn concurrent_asks_get_their_own_replies() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::matching("slow").wait(ms(2000)).reply("slow answer"));
+    bot.add_rule(Rule::matching("fast").wait(ms(100)).reply("fast answer"));
+    let client = connect(&bot).await;
+
+    let (slow, fast) = tokio::join!(
+        client.ask("slow", RequestPriority::Normal),
+        client.ask("fast", RequestPriority::Normal),
+    );
+    assert_eq!(slow.unwrap().text, "slow answer");
+    assert_eq!(fast.unwrap().text, "fast answer");
+}
+
+#[tokio::test(start_paused = true)]
+async fn unquoted_replies_go_to_the_oldest_waiting_prompt() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::any().wait(ms(300)).reply("answer").unquoted());
+    let client = connect(&bot).await;
+
+    let reply = client.ask("question", RequestPriority::Normal).await.unwrap();
+    assert_eq!(reply.text, "answer");
+    assert_eq!(reply.reply_to, None);
+    assert_eq!(reply.request_message_id, Some(bot.received()[0].id));
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_reply_to_a_plain_send_is_not_taken_by_a_later_ask() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::matching("Hello").wait(ms(300)).reply("reply to hello"));
+    bot.add_rule(Rule::any().wait(ms(300)).reply("reply to question"));
+    let client = connect(&bot).await;
+
+    client.send("Hello!", RequestPriority::High).await.unwrap();
+    let reply = client
+        .ask("What can you do?", RequestPriority::Normal)
+        .await
+        .unwrap();
+    assert_eq!(reply.text, "reply to question");
+    assert_eq!(reply.request_message_id, Some(bot.received()[1].id));
+}
+
+#[tokio::test(start_paused = true)]
+async fn ask_times_out_when_the_bot_stays_silent() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::any().silent());
+    let client = connect(&bot).await;
+
+    let err = client.ask("ping", RequestPriority::Normal).await.unwrap_err();
+    assert!(matches!(err, GrokError::Timeout(d) if d == Duration::from_secs(5)));
+    assert_eq!(bot.prompts(), ["ping"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn ask_returns_the_first_of_several_messages() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::any().reply("first").reply("second"));
+    let client = connect(&bot).await;
+
+    let reply = client.ask("hi", RequestPriority::Normal).await.unwrap();
+    assert_eq!(reply.text, "first");
+}
+
+#[tokio::test(start_paused = true)]
+async fn ask_stream_follows_edits_until_the_bot_goes_quiet() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(
+        Rule::any()
+            .reply("Hel")
+            .wait(ms(100))
+            .edit("Hello")
+            .wait(ms(100))
+            .edit("Hello wor")
+            .wait(ms(100))
+            .edit("Hello world"),
+    );
+    let client = connect(&bot).await;
+
+    let mut stream = client.ask_stream("hi", RequestPriority::High).await.unwrap();
+    let mut deltas = Vec::new();
+    let mut last = None;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.unwrap();
+        deltas.push(chunk.delta.clone());
+        last = Some(chunk);
+    }
+
+    let last = last.unwrap();
+    assert!(last.is_final);
+    assert_eq!(last.text, "Hello world");
+    assert_eq!(deltas.concat(), "Hello world");
+}
+
+#[tokio::test(start_paused = true)]
+async fn ask_stream_stops_at_the_final_marker() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::any().reply("Thinking").edit("Done ✅").wait(ms(100)).edit("ignored"));
+    let mut config = bot.config();
+    config.stream_final_marker = Some("✅".into());
+    let client = GrokClient::with_transport(config, bot.transport()).await.unwrap();
+    client.start();
+
+    let stream = client.ask_stream("hi", RequestPriority::Normal).await.unwrap();
+    assert_eq!(stream.final_text().await.unwrap(), "Done ✅");
+}
+
+#[tokio::test(start_paused = true)]
+async fn ask_stream_fails_when_the_client_shuts_down_mid_reply() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::any().reply("part").wait(ms(300)).edit("part and the rest"));
+    let client = GrokClient::with_transport(bot.config(), bot.transport()).await.unwrap();
+    let handle = client.start();
+
+    let mut stream = client.ask_stream("hi", RequestPriority::Normal).await.unwrap();
+    let first = stream.next().await.unwrap().unwrap();
+    assert_eq!((first.text.as_str(), first.is_final), ("part", false));
+
+    handle.shutdown(ms(0)).await;
+    assert!(matches!(stream.final_text().await, Err(GrokError::ShuttingDown)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn ask_stream_waits_for_its_prompt_to_be_sent() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::any().wait(ms(1000)).reply("late").wait(ms(100)).edit("late but whole"));
+    let client = connect(&bot).await;
+
+    bot.transport().flood_wait(Duration::from_secs(10));
+    let stream = client.ask_stream("hi", RequestPriority::Normal).await.unwrap();
+    assert_eq!(stream.final_text().await.unwrap(), "late but whole");
+}
+
+#[tokio::test(start_paused = true)]
+async fn dropping_an_ask_stream_takes_it_off_the_queue() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::any().reply("ok"));
+    let client = GrokClient::with_transport(bot.config(), bot.transport()).await.unwrap();
+
+    let stream = client.ask_stream("never mind", RequestPriority::Normal).await.unwrap();
+    tokio::time::sleep(ms(10)).await;
+    drop(stream);
+    tokio::time::sleep(ms(10)).await;
+
+    client.start();
+    client.send("this one", RequestPriority::Normal).await.unwrap().wait().await;
+    assert_eq!(bot.prompts(), ["this one"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn sender_drains_the_queue_by_priority() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = GrokClient::with_transport(bot.config(), bot.transport()).await.unwrap();
+
+    client.send("low", RequestPriority::Low).await.unwrap();
+    client.send("normal", RequestPriority::Normal).await.unwrap();
+    client.send("emergency", RequestPriority::Emergency).await.unwrap();
+    client.send("high", RequestPriority::High).await.unwrap();
+    client.start();
+
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert_eq!(bot.prompts(), ["emergency", "high", "normal", "low"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn new_rejects_a_bot_username_that_is_a_channel() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.transport().add_user("GrokAI", BOT_ID, PeerKind::Channel);
+
+    let result = GrokClient::with_transport(bot.config(), bot.transport()).await;
+    assert!(matches!(result, Err(GrokError::Bot(_))));
 }
\ No newline at end of file
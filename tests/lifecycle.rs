@@ -0,0 +1,131 @@
+use grok_client::prelude::*;
+use grok_client::testing::{FakeBot, Rule};
+use std::time::Duration;
+
+mod common;
+use common::{ms, BOT_ID};
+
+async fn client_for(bot: &FakeBot) -> GrokClient {
+    GrokClient::with_transport(bot.config(), bot.transport()).await.unwrap()
+}
+
+#[tokio::test(start_paused = true)]
+async fn shutdown_drains_the_queue_and_rejects_new_messages() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = client_for(&bot).await;
+    for i in 0..5 {
+        client.send(&format!("m{i}"), RequestPriority::Normal).await.unwrap();
+    }
+
+    let handle = client.start();
+    let report = handle.shutdown(Duration::from_secs(10)).await;
+
+    assert!(report.is_clean(), "{report:?}");
+    assert_eq!(bot.prompts(), ["m0", "m1", "m2", "m3", "m4"]);
+    assert!(matches!(
+        client.send("late", RequestPriority::High).await,
+        Err(GrokError::ShuttingDown)
+    ));
+}
+
+#[tokio::test(start_paused = true)]
+async fn shutdown_returns_what_it_could_not_send_before_the_deadline() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = client_for(&bot).await;
+    for i in 0..20 {
+        client.send(&format!("m{i}"), RequestPriority::Low).await.unwrap();
+    }
+    client.send("urgent", RequestPriority::Emergency).await.unwrap();
+
+    let handle = client.start();
+    let report = handle.shutdown(ms(350)).await;
+
+    let sent = bot.prompts();
+    assert_eq!(sent[0], "urgent");
+    assert_eq!(sent.len() + report.unsent.len(), 21);
+    assert!(!report.unsent.is_empty());
+    assert!(report.unsent.iter().all(|(_, p)| *p == RequestPriority::Low));
+}
+
+#[tokio::test(start_paused = true)]
+async fn shutdown_fails_requests_still_waiting_for_a_reply() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::any().silent());
+    let client = client_for(&bot).await;
+    let handle = client.start();
+
+    let ask = client.ask("anyone?", RequestPriority::Normal);
+    let shutdown = async {
+        tokio::time::sleep(ms(500)).await;
+        handle.shutdown(Duration::from_secs(1)).await
+    };
+    let (reply, report) = tokio::join!(ask, shutdown);
+
+    assert!(matches!(reply, Err(GrokError::ShuttingDown)));
+    assert!(report.is_clean());
+}
+
+#[tokio::test(start_paused = true)]
+async fn shutdown_reports_panicked_tasks() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = client_for(&bot).await;
+    client.add_custom_handler(handler_fn(|_ctx: MessageContext| async move {
+        panic!("handler bug");
+    }));
+    let handle = client.start();
+
+    bot.say("boom");
+    tokio::time::sleep(ms(50)).await;
+    assert!(handle.is_finished());
+
+    let report = handle.shutdown(Duration::from_secs(1)).await;
+    assert_eq!(report.panicked, ["dispatcher"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn shutdown_aborts_a_handler_still_running_after_the_deadline() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = client_for(&bot).await;
+    client.add_custom_handler(handler_fn(|_ctx: MessageContext| async move {
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        Propagation::Continue
+    }));
+    let handle = client.start();
+
+    bot.say("take your time");
+    tokio::time::sleep(ms(50)).await;
+
+    let shutdown = handle.shutdown(Duration::from_secs(1));
+    let report = tokio::time::timeout(Duration::from_secs(5), shutdown)
+        .await
+        .expect("shutdown should not wait for the handler");
+    assert_eq!(report.aborted, ["dispatcher"]);
+    assert!(!report.is_clean());
+}
+
+#[tokio::test(start_paused = true)]
+async fn handlers_cannot_queue_replies_once_shutdown_starts() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = client_for(&bot).await;
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let tx = std::sync::Mutex::new(Some(tx));
+    client.add_custom_handler(handler_fn(move |ctx: MessageContext| {
+        let tx = tx.lock().unwrap().take();
+        async move {
+            tokio::time::sleep(ms(500)).await;
+            if let Some(tx) = tx {
+                let _ = tx.send(ctx.reply("late").await);
+            }
+            Propagation::Continue
+        }
+    }));
+    let handle = client.start();
+
+    bot.say("hello");
+    tokio::time::sleep(ms(50)).await;
+    let report = handle.shutdown(Duration::from_secs(1)).await;
+
+    assert!(matches!(rx.await.unwrap(), Err(GrokError::ShuttingDown)));
+    assert!(report.unsent.is_empty(), "{report:?}");
+    assert!(bot.prompts().is_empty());
+}
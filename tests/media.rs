@@ -0,0 +1,100 @@
+use grok_client::media::Upload;
+use grok_client::prelude::*;
+use grok_client::testing::{FakeBot, Rule};
+use grok_client::transport::{MediaKind, OutgoingMessage};
+
+mod common;
+use common::{connect, BOT_ID};
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\nnot really an image";
+
+#[tokio::test]
+async fn a_photo_from_memory_goes_out_with_its_caption() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::matching("^What is on").reply("A cat."));
+    let client = connect(&bot).await;
+
+    let message = OutgoingMessage::text("What is on this screenshot?")
+        .with_media(Upload::photo_bytes(PNG, "screenshot.png").with_mime_type("image/png"));
+    let reply = client.ask(message, RequestPriority::Normal).await.unwrap();
+    assert_eq!(reply.text, "A cat.");
+
+    let sent = &bot.received()[0].message;
+    assert_eq!(sent.text, "What is on this screenshot?");
+    let upload = sent.media.as_ref().unwrap();
+    assert_eq!(upload.kind(), MediaKind::Photo);
+    assert_eq!(upload.name(), "screenshot.png");
+    assert_eq!(upload.read().await.unwrap(), PNG);
+}
+
+#[tokio::test]
+async fn documents_and_voice_notes_are_sent_from_files() {
+    let dir = std::env::temp_dir().join(format!("grok-client-media-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let report = dir.join("report.pdf");
+    let note = dir.join("question.ogg");
+    std::fs::write(&report, b"%PDF-1.7").unwrap();
+    std::fs::write(&note, b"OggS").unwrap();
+
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = connect(&bot).await;
+    client
+        .send(Upload::document(&report), RequestPriority::Normal)
+        .await
+        .unwrap();
+    client
+        .send(
+            OutgoingMessage::text("Listen").with_media(Upload::voice(&note)),
+            RequestPriority::Normal,
+        )
+        .await
+        .unwrap();
+    tokio::time::sleep(common::ms(300)).await;
+
+    let sent: Vec<_> = bot.received().into_iter().map(|s| s.message).collect();
+    assert_eq!(sent.len(), 2);
+    let document = sent[0].media.as_ref().unwrap();
+    assert_eq!((document.kind(), document.name()), (MediaKind::Document, "report.pdf"));
+    assert_eq!(sent[0].text, "");
+    let voice = sent[1].media.as_ref().unwrap();
+    assert_eq!(voice.kind(), MediaKind::Voice);
+    assert_eq!(voice.mime_type(), Some("audio/ogg"));
+    assert_eq!(voice.read().await.unwrap(), b"OggS");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn a_missing_file_fails_the_request() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = connect(&bot).await;
+
+    let result = client
+        .ask(Upload::photo("/nonexistent/screenshot.png"), RequestPriority::Normal)
+        .await;
+    assert!(matches!(result, Err(GrokError::Io(_))), "{result:?}");
+    assert!(bot.received().is_empty());
+}
+
+#[tokio::test]
+async fn media_in_a_reply_can_be_downloaded() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::matching("^Draw").reply_media(
+        "Here you go",
+        Upload::photo_bytes(PNG, "cat.png").with_mime_type("image/png"),
+    ));
+    let client = connect(&bot).await;
+
+    let reply = client.ask("Draw a cat", RequestPriority::Normal).await.unwrap();
+    assert_eq!(reply.text, "Here you go");
+    let image = reply.media.expect("an attachment");
+    assert_eq!(image.kind, MediaKind::Photo);
+    assert_eq!(image.mime_type.as_deref(), Some("image/png"));
+    assert_eq!(image.size, PNG.len() as u64);
+    assert_eq!(image.file_name.as_deref(), Some("cat.png"));
+    assert_eq!(client.download(&image).await.unwrap(), PNG);
+
+    let path = std::env::temp_dir().join(format!("grok-client-cat-{}.png", std::process::id()));
+    client.download_to(&image, &path).await.unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), PNG);
+    std::fs::remove_file(&path).unwrap();
+}
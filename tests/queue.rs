@@ -0,0 +1,19 @@
+use grok_client::queue::PriorityQueue;
+use grok_client::transport::OutgoingMessage;
+use grok_client::RequestPriority;
+
+#[test]
+fn pops_highest_priority_first_and_fifo_within_a_priority() {
+    let mut queue = PriorityQueue::new();
+    queue.push(OutgoingMessage::text("low"), RequestPriority::Low);
+    queue.push(OutgoingMessage::text("normal 1"), RequestPriority::Normal);
+    queue.push(OutgoingMessage::text("emergency"), RequestPriority::Emergency);
+    queue.push(OutgoingMessage::text("normal 2"), RequestPriority::Normal);
+    queue.push(OutgoingMessage::text("high"), RequestPriority::High);
+
+    let order: Vec<_> = std::iter::from_fn(|| queue.pop())
+        .map(|(message, _)| message.text)
+        .collect();
+    assert_eq!(order, ["emergency", "high", "normal 1", "normal 2", "low"]);
+    assert!(queue.is_empty());
+}
@@ -0,0 +1,139 @@
+use grok_client::media::Upload;
+use grok_client::prelude::*;
+use grok_client::testing::{FakeBot, Rule};
+use grok_client::RequestStatus;
+use std::time::Duration;
+
+mod common;
+use common::{ms, BOT_ID};
+
+async fn client_for(bot: &FakeBot) -> GrokClient {
+    let mut config = bot.config();
+    config.response_timeout = 5;
+    GrokClient::with_transport(config, bot.transport())
+        .await
+        .unwrap()
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_sent_message_is_followed_until_it_is_sent() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = client_for(&bot).await;
+
+    let mut first = client.send("one", RequestPriority::Normal).await.unwrap();
+    let second = client.send("two", RequestPriority::Normal).await.unwrap();
+    assert_ne!(first.id(), second.id());
+    assert_eq!(first.status(), RequestStatus::Queued);
+
+    client.start();
+    let status = first.wait().await;
+    let sent = &bot.received()[0];
+    assert_eq!(sent.message.text, "one");
+    assert_eq!(
+        status,
+        RequestStatus::Sent {
+            message_id: sent.id
+        }
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn queued_messages_can_be_cancelled() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = client_for(&bot).await;
+
+    let mut kept = client.send("kept", RequestPriority::Normal).await.unwrap();
+    let dropped = client
+        .send("dropped", RequestPriority::Normal)
+        .await
+        .unwrap();
+    assert!(dropped.cancel().await);
+    assert_eq!(dropped.status(), RequestStatus::Cancelled);
+
+    client.start();
+    kept.wait().await;
+    tokio::time::sleep(ms(500)).await;
+    assert_eq!(bot.prompts(), ["kept"]);
+    // Too late once it is out
+    assert!(!kept.cancel().await);
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_request_is_followed_until_it_is_answered() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::any().wait(ms(100)).reply("hi"));
+    let client = client_for(&bot).await;
+    client.start();
+
+    let mut request = client
+        .request("hello", RequestPriority::Normal)
+        .await
+        .unwrap();
+    let status = request.wait().await;
+    let RequestStatus::Answered {
+        message_id,
+        reply_id,
+    } = status
+    else {
+        panic!("expected an answer, got {:?}", status);
+    };
+    assert_eq!(message_id, bot.received()[0].id);
+
+    let reply = request.reply().await.unwrap();
+    assert_eq!((reply.message_id, reply.text.as_str()), (reply_id, "hi"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn unanswered_requests_expire_and_failed_sends_are_reported() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::any().silent());
+    let client = client_for(&bot).await;
+    client.start();
+
+    let mut request = client
+        .request("anyone?", RequestPriority::Normal)
+        .await
+        .unwrap();
+    assert_eq!(request.wait().await, RequestStatus::Expired);
+
+    let missing = std::env::temp_dir().join("grok-client-requests-missing.png");
+    let mut upload = client
+        .send(Upload::photo(missing), RequestPriority::Normal)
+        .await
+        .unwrap();
+    assert!(matches!(upload.wait().await, RequestStatus::Failed(_)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn dropping_an_ask_takes_it_off_the_queue() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::any().reply("ok"));
+    let client = client_for(&bot).await;
+
+    let ask = client.ask("never mind", RequestPriority::Normal);
+    assert!(tokio::time::timeout(ms(10), ask).await.is_err());
+
+    client.start();
+    let reply = client
+        .ask("this one", RequestPriority::Normal)
+        .await
+        .unwrap();
+    assert_eq!(reply.text, "ok");
+    assert_eq!(bot.prompts(), ["this one"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn the_reply_timeout_starts_once_the_message_is_sent() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::any().wait(ms(1000)).reply("worth the wait"));
+    let client = client_for(&bot).await;
+    client.start();
+
+    bot.transport().flood_wait(Duration::from_secs(10));
+    let reply = client
+        .ask("patience", RequestPriority::Normal)
+        .await
+        .unwrap();
+    assert_eq!(reply.text, "worth the wait");
+    assert_eq!(bot.prompts(), ["patience"]);
+}
@@ -0,0 +1,356 @@
+use grok_client::auth::{AuthQuestion, ChannelPrompter};
+use grok_client::prelude::*;
+use grok_client::testing::FakeBot;
+use grok_client::session::encrypt_session_file;
+use grok_client::{
+    EncryptedSessionStore, FileSessionStore, MemorySessionStore, SessionKey, SessionLock,
+    SessionStore, StringSessionStore,
+};
+use std::time::Duration;
+
+mod common;
+use common::BOT_ID;
+
+#[test]
+fn a_file_store_round_trips_at_its_own_path() {
+    let dir = std::env::temp_dir().join(format!("grok-client-session-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("custom.session");
+    let store = FileSessionStore::new(&path);
+
+    assert_eq!(store.load().unwrap(), None);
+    store.save(b"first").unwrap();
+    store.save(b"second").unwrap();
+
+    assert_eq!(store.load().unwrap().as_deref(), Some(&b"second"[..]));
+    assert_eq!(std::fs::read(&path).unwrap(), b"second");
+    let leftovers: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+    assert_eq!(leftovers.len(), 1, "{leftovers:?}");
+
+    std::fs::write(&path, b"").unwrap();
+    assert_eq!(store.load().unwrap(), None);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn memory_store_clones_share_the_session() {
+    let store = MemorySessionStore::new();
+    let clone = store.clone();
+
+    assert_eq!(store.load().unwrap(), None);
+    clone.save(b"session").unwrap();
+    assert_eq!(store.get().as_deref(), Some(&b"session"[..]));
+}
+
+#[test]
+fn a_string_store_keeps_the_session_as_base64() {
+    let store = StringSessionStore::default();
+    assert_eq!(store.load().unwrap(), None);
+
+    store.save(b"\x00\x01session").unwrap();
+    let encoded = store.get();
+    assert_eq!(encoded, "AAFzZXNzaW9u");
+
+    let restored = StringSessionStore::new(encoded);
+    assert_eq!(restored.load().unwrap().as_deref(), Some(&b"\x00\x01session"[..]));
+
+    let broken = StringSessionStore::new("not base64!");
+    assert!(matches!(broken.load(), Err(GrokError::Session(_))));
+}
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("grok-client-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn an_encrypted_store_round_trips_and_hides_the_session() {
+    let inner = MemorySessionStore::new();
+    let store = EncryptedSessionStore::new(inner.clone(), SessionKey::from_passphrase("hunter2"));
+
+    assert_eq!(store.load().unwrap(), None);
+    store.save(b"auth key").unwrap();
+    store.save(b"auth key, later").unwrap();
+
+    let stored = inner.get().unwrap();
+    assert!(!stored.windows(8).any(|w| w == b"auth key"));
+    assert_eq!(store.load().unwrap().as_deref(), Some(&b"auth key, later"[..]));
+
+    let reopened = EncryptedSessionStore::new(inner, SessionKey::from_passphrase("hunter2"));
+    assert_eq!(reopened.load().unwrap().as_deref(), Some(&b"auth key, later"[..]));
+}
+
+#[test]
+fn an_encrypted_store_rejects_a_wrong_key_or_tampering() {
+    let inner = MemorySessionStore::new();
+    EncryptedSessionStore::new(inner.clone(), SessionKey::from_passphrase("right"))
+        .save(b"auth key")
+        .unwrap();
+
+    let wrong = EncryptedSessionStore::new(inner.clone(), SessionKey::from_passphrase("wrong"));
+    assert!(matches!(wrong.load(), Err(GrokError::Session(_))));
+
+    let mut data = inner.get().unwrap();
+    let last = data.len() - 1;
+    data[last] ^= 1;
+    inner.save(&data).unwrap();
+    let right = EncryptedSessionStore::new(inner.clone(), SessionKey::from_passphrase("right"));
+    assert!(matches!(right.load(), Err(GrokError::Session(_))));
+
+    inner.save(b"plain session").unwrap();
+    assert!(matches!(right.load(), Err(GrokError::Session(_))));
+}
+
+#[cfg(unix)]
+#[test]
+fn session_files_are_private_and_loose_ones_are_refused() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = temp_dir("private");
+    let path = dir.join("grok.session");
+    let key = || SessionKey::from_passphrase("secret");
+    EncryptedSessionStore::file(&path, key()).save(b"auth key").unwrap();
+
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+    let err = EncryptedSessionStore::file(&path, key()).load().unwrap_err();
+    assert!(err.to_string().contains("chmod 600"), "{err}");
+
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+    let loaded = EncryptedSessionStore::file(&path, key()).load().unwrap();
+    assert_eq!(loaded.as_deref(), Some(&b"auth key"[..]));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_plain_session_file_can_be_encrypted_in_place_with_a_keyfile() {
+    let dir = temp_dir("migrate");
+    let path = dir.join("session.session");
+    let keyfile = dir.join("session.key");
+    let plain = grammers_session::Session::new().save();
+    std::fs::write(&path, &plain).unwrap();
+
+    SessionKey::generate_keyfile(&keyfile).unwrap();
+    assert!(SessionKey::generate_keyfile(&keyfile).is_err());
+    encrypt_session_file(&path, &path, SessionKey::from_keyfile(&keyfile).unwrap()).unwrap();
+    assert_ne!(std::fs::read(&path).unwrap(), plain);
+
+    let store = EncryptedSessionStore::file(&path, SessionKey::from_keyfile(&keyfile).unwrap());
+    assert_eq!(store.load().unwrap(), Some(plain));
+
+    let again = encrypt_session_file(&path, &path, SessionKey::from_keyfile(&keyfile).unwrap());
+    assert!(matches!(again, Err(GrokError::Session(_))));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn signing_in_saves_the_session() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.transport().require_login("12345", None);
+    let (prompter, mut questions) = ChannelPrompter::new();
+    tokio::spawn(async move {
+        while let Some(request) = questions.next().await {
+            let answer = match request.question {
+                AuthQuestion::Phone => "+1234567890",
+                _ => "12345",
+            };
+            request.answer(answer);
+        }
+    });
+
+    GrokClient::builder(bot.config())
+        .transport(bot.transport())
+        .prompter(prompter)
+        .build()
+        .await
+        .unwrap();
+
+    assert_eq!(bot.transport().session_saves(), 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_started_client_saves_periodically_and_on_shutdown() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let mut config = bot.config();
+    config.session_save_interval_secs = 60;
+    let client = GrokClient::with_transport(config, bot.transport()).await.unwrap();
+    let handle = client.start();
+
+    tokio::time::sleep(Duration::from_secs(150)).await;
+    assert_eq!(bot.transport().session_saves(), 2);
+
+    let report = handle.shutdown(Duration::from_secs(1)).await;
+    assert!(report.is_clean(), "{report:?}");
+    assert_eq!(bot.transport().session_saves(), 3);
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_zero_interval_only_saves_on_shutdown() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let mut config = bot.config();
+    config.session_save_interval_secs = 0;
+    let client = GrokClient::with_transport(config, bot.transport()).await.unwrap();
+    let handle = client.start();
+
+    tokio::time::sleep(Duration::from_secs(3600)).await;
+    assert_eq!(bot.transport().session_saves(), 0);
+
+    handle.shutdown(Duration::from_secs(1)).await;
+    assert_eq!(bot.transport().session_saves(), 1);
+}
+
+#[test]
+fn a_session_lock_is_exclusive_until_dropped() {
+    let dir = temp_dir("lock");
+    let session = dir.join("grok.session");
+
+    let lock = SessionLock::acquire(&session).unwrap();
+    assert_eq!(lock.path(), dir.join("grok.session.lock"));
+    let owner = std::fs::read_to_string(lock.path()).unwrap();
+    assert!(owner.starts_with(&format!("{}\n", std::process::id())), "{owner}");
+
+    match SessionLock::acquire(&session) {
+        Err(GrokError::SessionLocked { pid, host, .. }) => {
+            assert_eq!(pid, std::process::id());
+            assert_eq!(host, owner.lines().nth(1).unwrap());
+        }
+        other => panic!("expected SessionLocked, got {other:?}"),
+    }
+
+    drop(lock);
+    assert!(!dir.join("grok.session.lock").exists());
+    SessionLock::acquire(&session).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn a_lock_left_by_a_dead_process_is_taken_over() {
+    let dir = temp_dir("stale-lock");
+    let session = dir.join("grok.session");
+    let host = {
+        let lock = SessionLock::acquire(&session).unwrap();
+        let owner = std::fs::read_to_string(lock.path()).unwrap();
+        owner.lines().nth(1).unwrap().to_string()
+    };
+    let mut child = std::process::Command::new("true").spawn().unwrap();
+    let dead = child.id();
+    child.wait().unwrap();
+
+    std::fs::write(dir.join("grok.session.lock"), format!("{dead}\n{host}\n")).unwrap();
+    let lock = SessionLock::acquire(&session).unwrap();
+    let owner = std::fs::read_to_string(lock.path()).unwrap();
+    assert!(owner.starts_with(&format!("{}\n", std::process::id())), "{owner}");
+
+    // A process on another host cannot be checked, so its lock stands
+    drop(lock);
+    std::fs::write(dir.join("grok.session.lock"), format!("{dead}\nelsewhere\n")).unwrap();
+    let err = SessionLock::acquire(&session).unwrap_err();
+    assert!(err.to_string().contains("elsewhere"), "{err}");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn only_one_process_takes_over_a_stale_lock() {
+    let dir = temp_dir("stale-race");
+    let session = dir.join("grok.session");
+    let host = {
+        let lock = SessionLock::acquire(&session).unwrap();
+        let owner = std::fs::read_to_string(lock.path()).unwrap();
+        owner.lines().nth(1).unwrap().to_string()
+    };
+    let mut child = std::process::Command::new("true").spawn().unwrap();
+    let dead = child.id();
+    child.wait().unwrap();
+
+    for _ in 0..200 {
+        std::fs::write(dir.join("grok.session.lock"), format!("{dead}\n{host}\n")).unwrap();
+        let tried = std::sync::Barrier::new(8);
+        let held = std::thread::scope(|scope| {
+            let takers: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let lock = SessionLock::acquire(&session);
+                        tried.wait();
+                        lock.is_ok()
+                    })
+                })
+                .collect();
+            takers.into_iter().map(|t| t.join().unwrap()).filter(|&held| held).count()
+        });
+        assert_eq!(held, 1);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn a_second_client_on_a_locked_session_fails() {
+    let dir = temp_dir("client-lock");
+    let mut config = FakeBot::new("GrokAI", BOT_ID).config();
+    config.session_path = dir.join("grok.session");
+    let _lock = SessionLock::acquire(&config.session_path).unwrap();
+
+    let result = GrokClient::new(config).await;
+    assert!(matches!(result, Err(GrokError::SessionLocked { .. })));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn a_session_file_given_to_the_builder_is_locked_too() {
+    let dir = temp_dir("builder-lock");
+    let mut config = FakeBot::new("GrokAI", BOT_ID).config();
+    config.session_path = dir.join("grok.session");
+    let _lock = SessionLock::acquire(&config.session_path).unwrap();
+
+    let key = SessionKey::from_passphrase("pw");
+    let store = EncryptedSessionStore::file(&config.session_path, key);
+    let result = GrokClient::builder(config).session_store(store).build().await;
+    assert!(matches!(result, Err(GrokError::SessionLocked { .. })));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn clearing_a_store_forgets_the_session() {
+    let dir = temp_dir("clear");
+    let path = dir.join("grok.session");
+    let file = FileSessionStore::new(&path);
+    file.save(b"auth key").unwrap();
+    file.clear().unwrap();
+    assert!(!path.exists());
+    file.clear().unwrap();
+
+    let memory = MemorySessionStore::new();
+    let encrypted = EncryptedSessionStore::new(memory.clone(), SessionKey::from_passphrase("pw"));
+    encrypted.save(b"auth key").unwrap();
+    encrypted.clear().unwrap();
+    assert_eq!(memory.get(), None);
+    assert_eq!(encrypted.load().unwrap(), None);
+
+    let string = StringSessionStore::new("YXV0aCBrZXk=");
+    string.clear().unwrap();
+    assert_eq!(string.load().unwrap(), None);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_file_store_keeps_trusted_bots_next_to_the_session() {
+    let dir = temp_dir("trust");
+    let path = dir.join("grok.session");
+    let store = FileSessionStore::new(&path);
+
+    assert_eq!(store.trusted_bot("GrokAI").unwrap(), None);
+    store.trust_bot("GrokAI", 42).unwrap();
+    store.trust_bot("OtherBot", 7).unwrap();
+
+    let reopened = FileSessionStore::new(&path);
+    assert_eq!(reopened.trusted_bot("grokai").unwrap(), Some(42));
+    assert_eq!(reopened.trusted_bot("OtherBot").unwrap(), Some(7));
+    assert!(dir.join("grok.session.bots").exists());
+    assert_eq!(reopened.load().unwrap(), None);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,117 @@
+use grok_client::prelude::*;
+use grok_client::RateLimit;
+use grok_client::testing::{FakeBot, Rule};
+use std::time::Duration;
+
+mod common;
+use common::{connect, ms, BOT_ID};
+
+#[tokio::test(start_paused = true)]
+async fn flood_wait_pauses_the_queue_and_retries_the_message() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::any().reply("ok"));
+    let client = connect(&bot).await;
+
+    bot.transport().flood_wait(Duration::from_secs(3));
+    client.send("first", RequestPriority::Low).await.unwrap();
+    client.send("second", RequestPriority::Low).await.unwrap();
+
+    tokio::time::sleep(ms(2500)).await;
+    assert!(bot.prompts().is_empty());
+    let stats = client.flood_waits();
+    assert_eq!(stats.count, 1);
+    assert_eq!(stats.total, Duration::from_secs(3));
+    assert!(stats.paused_until.is_some());
+
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert_eq!(bot.prompts(), ["first", "second"]);
+    assert_eq!(client.flood_waits().paused_until, None);
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_request_survives_a_flood_wait() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    bot.add_rule(Rule::any().reply("ok"));
+    let client = connect(&bot).await;
+
+    bot.transport().flood_wait(Duration::from_secs(2));
+    let reply = client.ask("hello", RequestPriority::High).await.unwrap();
+
+    assert_eq!(reply.text, "ok");
+    assert_eq!(bot.prompts(), ["hello"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn shutdown_does_not_wait_out_a_long_flood_wait() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = GrokClient::with_transport(bot.config(), bot.transport()).await.unwrap();
+    bot.transport().flood_wait(Duration::from_secs(600));
+    client.send("stuck", RequestPriority::Normal).await.unwrap();
+    let handle = client.start();
+
+    tokio::time::sleep(ms(200)).await;
+    let started = tokio::time::Instant::now();
+    let report = handle.shutdown(Duration::from_secs(1)).await;
+
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert_eq!(report.unsent.len(), 1);
+    assert_eq!(report.unsent[0].0.text, "stuck");
+}
+
+async fn connect_with(bot: &FakeBot, configure: impl FnOnce(&mut GrokConfig)) -> GrokClient {
+    let mut config = bot.config();
+    configure(&mut config);
+    let client = GrokClient::with_transport(config, bot.transport()).await.unwrap();
+    client.start();
+    client
+}
+
+#[tokio::test(start_paused = true)]
+async fn rate_limit_allows_a_burst_then_refills() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = connect_with(&bot, |c| c.rate_limit = RateLimit::new(1.0, 3)).await;
+    for i in 0..5 {
+        client.send(&format!("m{i}"), RequestPriority::Normal).await.unwrap();
+    }
+
+    tokio::time::sleep(ms(10)).await;
+    assert_eq!(bot.prompts().len(), 3);
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert_eq!(bot.prompts().len(), 4);
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert_eq!(bot.prompts(), ["m0", "m1", "m2", "m3", "m4"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_throttled_priority_does_not_hold_up_the_others() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = connect_with(&bot, |c| {
+        c.rate_limit = RateLimit::new(100.0, 10);
+        c.priority_rate_limits
+            .insert(RequestPriority::High, RateLimit::new(0.5, 1));
+    })
+    .await;
+    for i in 0..3 {
+        client.send(&format!("high{i}"), RequestPriority::High).await.unwrap();
+    }
+    for i in 0..2 {
+        client.send(&format!("low{i}"), RequestPriority::Low).await.unwrap();
+    }
+
+    tokio::time::sleep(ms(100)).await;
+    assert_eq!(bot.prompts(), ["high0", "low0", "low1"]);
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert_eq!(bot.prompts(), ["high0", "low0", "low1", "high1"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn an_idle_sender_wakes_as_soon_as_something_is_queued() {
+    let bot = FakeBot::new("GrokAI", BOT_ID);
+    let client = connect(&bot).await;
+
+    tokio::time::sleep(Duration::from_secs(10)).await;
+    client.send("hello", RequestPriority::Normal).await.unwrap();
+    tokio::time::sleep(ms(1)).await;
+
+    assert_eq!(bot.prompts(), ["hello"]);
+}